@@ -3,26 +3,48 @@
 /// BMI160 is a accelerometer from Bosch
 
 use embedded_hal::i2c::I2c;
+use crate::bmi160_config::{AccelOdr, AccelRange, Bandwidth, CalibrationPose, Config, GyroOdr, GyroRange, StepMode};
+use crate::bmi160_fifo::{FifoConfig, FifoFrames};
+use crate::bmi160_interrupt::*;
 use crate::bmi160_registers::*;
 use crate::bmi160_error::*;
 
 const ADDR: u8 = 0x15;
 const TEMP_REGISTER: u8 = 0x15;
+const STEP_CNT_EN: u8 = 0x08;
+// STATUS bits
+const FOC_RDY: u8 = 0x08;
+const NVM_RDY: u8 = 0x10;
+// OFFSET_6 bits
+const ACC_OFF_EN: u8 = 0x40;
+const GYR_OFF_EN: u8 = 0x80;
+const NVM_PROG_EN: u8 = 0x02;
+/// Status polls before giving up, 10 ms apart
+const STATUS_POLLS: u16 = 50;
 
 
 pub struct Driver<I2C> {
     i2c: I2C,
     address: u8,
     calibration: Option<CalibrationData>,
+    config: Config,
+    fifo: FifoConfig,
     a_res: f32,
     g_res: f32,
     output_data: Option<OutputData>,
+    /// Kept so that `set_accel_range` can convert the thresholds for the new range
+    any_motion: Option<AnyMotionConfig>,
+    no_motion: Option<NoMotionConfig>,
+    tap: Option<TapConfig>,
+    /// Sensor step count at the last `step_total`
+    step_count: u16,
+    step_total: u32,
 }
 
 #[derive(Clone)]
 pub struct CalibrationData {
-    accel_bias: [f32; 3],
-    gyro_bias: [f32; 3],
+    pub accel_bias: [f32; 3],
+    pub gyro_bias: [f32; 3],
 }
 
 #[derive(Clone)]
@@ -32,7 +54,7 @@ pub struct OutputData {
     pub temperature: f32,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Vector {
     pub x: f32,
     pub y: f32,
@@ -40,7 +62,7 @@ pub struct Vector {
 }
 
 impl<I2C: I2c> Driver<I2C> {
-    pub fn new<F>(mut i2c: I2C, address: Option<u8>, calibration: Option<CalibrationData>, delay_fn: F) -> Result<Self, Error<I2C::Error>>
+    pub fn new<F>(mut i2c: I2C, address: Option<u8>, calibration: Option<CalibrationData>, config: Config, delay_fn: F) -> Result<Self, Error<I2C::Error>>
     where F: Fn(u16) {
         let address = address.unwrap_or(DEFAULT_ADDRESS);
 
@@ -63,27 +85,309 @@ impl<I2C: I2c> Driver<I2C> {
         i2c.write(address, &[CMD, 0x15])?;
         delay_fn(100);
 
-        // Set up full scale accel range +-16G
-        i2c.write(address, &[ACC_RANGE, 0x0C])?;
-        // Set up full scale gyro range +-2000dps
-        i2c.write(address, &[GYR_RANGE, 0x00])?;
-        // Set Accel ODR to 500hz, BWP mode to oversample 4, LPF of ~40.5hz
-        i2c.write(address, &[ACC_CONF, 0x0A])?;
-        // Set Gyro ODR to 500hz, BWP mode to oversample 4, LPF of ~34.15hz
-        i2c.write(address, &[GYR_CONF, 0x0A])?;
-
+        // Set up full scale ranges, output data rates and filters
+        i2c.write(address, &[ACC_RANGE, config.accel_range as u8])?;
+        i2c.write(address, &[GYR_RANGE, config.gyro_range as u8])?;
+        i2c.write(address, &[ACC_CONF, config.acc_conf()])?;
+        i2c.write(address, &[GYR_CONF, config.gyr_conf()])?;
 
         Ok(Self {
             i2c,
             address,
             calibration,
-            a_res: 16.0 / 32768.0,
-            g_res: 2000.0 / 32768.0,
+            config,
+            fifo: FifoConfig::default(),
+            a_res: config.accel_range.resolution(),
+            g_res: config.gyro_range.resolution(),
             output_data: None,
+            any_motion: None,
+            no_motion: None,
+            tap: None,
+            step_count: 0,
+            step_total: 0,
         })
     }
 
-    pub fn update(&mut self) -> Result<(), Error<I2C::Error>>{
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Readings from `update` are scaled to the new range right away. The thresholds of
+    /// `set_any_motion`, `set_no_motion` and `set_tap` are given in g and are reprogrammed for
+    /// the new range, so they can be set before or after it.
+    pub fn set_accel_range(&mut self, range: AccelRange) -> Result<(), Error<I2C::Error>> {
+        self.i2c.write(self.address, &[ACC_RANGE, range as u8])?;
+        self.config.accel_range = range;
+        self.a_res = range.resolution();
+        if let Some(config) = self.any_motion {
+            self.set_any_motion(config)?;
+        }
+        if let Some(config) = self.no_motion {
+            self.set_no_motion(config)?;
+        }
+        if let Some(config) = self.tap {
+            self.set_tap(config)?;
+        }
+        Ok(())
+    }
+
+    pub fn set_gyro_range(&mut self, range: GyroRange) -> Result<(), Error<I2C::Error>> {
+        self.i2c.write(self.address, &[GYR_RANGE, range as u8])?;
+        self.config.gyro_range = range;
+        self.g_res = range.resolution();
+        Ok(())
+    }
+
+    pub fn set_accel_rate(&mut self, odr: AccelOdr, bandwidth: Bandwidth) -> Result<(), Error<I2C::Error>> {
+        let config = Config { accel_odr: odr, accel_bandwidth: bandwidth, ..self.config };
+        self.i2c.write(self.address, &[ACC_CONF, config.acc_conf()])?;
+        self.config = config;
+        Ok(())
+    }
+
+    pub fn set_gyro_rate(&mut self, odr: GyroOdr, bandwidth: Bandwidth) -> Result<(), Error<I2C::Error>> {
+        let config = Config { gyro_odr: odr, gyro_bandwidth: bandwidth, ..self.config };
+        self.i2c.write(self.address, &[GYR_CONF, config.gyr_conf()])?;
+        self.config = config;
+        Ok(())
+    }
+
+    /// Selects what goes into the FIFO. Data already in it stays, see `flush_fifo`.
+    pub fn configure_fifo(&mut self, fifo: FifoConfig) -> Result<(), Error<I2C::Error>> {
+        self.i2c.write(self.address, &[FIFO_CONFIG_0, fifo.config_0(), fifo.config_1()])?;
+        self.fifo = fifo;
+        Ok(())
+    }
+
+    /// Empties the FIFO.
+    pub fn flush_fifo(&mut self) -> Result<(), Error<I2C::Error>> {
+        self.i2c.write(self.address, &[CMD, 0xB0])?;
+        Ok(())
+    }
+
+    /// Number of bytes in the FIFO.
+    pub fn fifo_length(&mut self) -> Result<u16, Error<I2C::Error>> {
+        let mut length: [u8; 2] = [0; 2];
+        self.i2c.write_read(self.address, &[FIFO_LENGTH_0], &mut length)?;
+        Ok((((length[1] & 0x07) as u16) << 8) | length[0] as u16)
+    }
+
+    /// Reads as much of the FIFO as fits in `buffer` in one burst, and returns its frames. A frame
+    /// cut off at the end of the buffer is skipped by the parser, and the sensor sends it again
+    /// in full on the next read. A buffer of `FIFO_SIZE` bytes holds the whole FIFO.
+    pub fn read_fifo<'b>(&mut self, buffer: &'b mut [u8]) -> Result<FifoFrames<'b>, Error<I2C::Error>> {
+        let length = (self.fifo_length()? as usize).min(buffer.len());
+        let data = &mut buffer[..length];
+        if length > 0 {
+            self.i2c.write_read(self.address, &[FIFO_DATA], data)?;
+        }
+        Ok(FifoFrames::new(data, self.fifo, self.a_res, self.g_res, self.calibration.clone()))
+    }
+
+    /// Sets the active level and driver of `pin` and enables it as an output.
+    pub fn configure_interrupt_pin(&mut self, pin: InterruptPin, config: PinConfig) -> Result<(), Error<I2C::Error>> {
+        let shift = match pin {
+            InterruptPin::Int1 => 0,
+            InterruptPin::Int2 => 4,
+        };
+        self.update_register(INT_OUT_CTRL, 0x0F << shift, config.out_ctrl() << shift)
+    }
+
+    pub fn set_interrupt_latch(&mut self, latch: Latch) -> Result<(), Error<I2C::Error>> {
+        self.update_register(INT_LATCH, 0x0F, latch as u8)
+    }
+
+    /// Clears latched interrupts.
+    pub fn reset_interrupts(&mut self) -> Result<(), Error<I2C::Error>> {
+        self.i2c.write(self.address, &[CMD, 0xB1])?;
+        Ok(())
+    }
+
+    pub fn set_any_motion(&mut self, config: AnyMotionConfig) -> Result<(), Error<I2C::Error>> {
+        self.update_register(INT_MOTION_0, 0b11, config.samples.clamp(1, 4) - 1)?;
+        self.i2c.write(self.address, &[INT_MOTION_1, threshold(config.threshold, self.accel_range(), 512.0)])?;
+        // Any-motion instead of significant motion
+        self.update_register(INT_MOTION_3, 0b10, 0b00)?;
+        self.any_motion = Some(config);
+        Ok(())
+    }
+
+    pub fn set_no_motion(&mut self, config: NoMotionConfig) -> Result<(), Error<I2C::Error>> {
+        self.update_register(INT_MOTION_0, 0b1111_1100, no_motion_duration(config.duration) << 2)?;
+        self.i2c.write(self.address, &[INT_MOTION_2, threshold(config.threshold, self.accel_range(), 512.0)])?;
+        // No-motion instead of slow motion
+        self.update_register(INT_MOTION_3, 0b01, 0b01)?;
+        self.no_motion = Some(config);
+        Ok(())
+    }
+
+    pub fn set_tap(&mut self, config: TapConfig) -> Result<(), Error<I2C::Error>> {
+        let tap_0 = ((config.short_quiet as u8) << 7) | ((config.long_shock as u8) << 6) | config.window as u8;
+        self.i2c.write(self.address, &[INT_TAP_0, tap_0])?;
+        let tap_1 = threshold(config.threshold, self.accel_range(), 32.0).min(0x1F);
+        self.i2c.write(self.address, &[INT_TAP_1, tap_1])?;
+        self.tap = Some(config);
+        Ok(())
+    }
+
+    pub fn set_orientation(&mut self, config: OrientationConfig) -> Result<(), Error<I2C::Error>> {
+        let orient_0 = ((config.hysteresis & 0x0F) << 4) | ((config.blocking & 0b11) << 2) | config.mode as u8;
+        self.i2c.write(self.address, &[INT_ORIENT_0, orient_0])?;
+        let orient_1 = ((config.swap_axes as u8) << 7) | ((config.up_down as u8) << 6) | (config.theta & 0x3F);
+        self.i2c.write(self.address, &[INT_ORIENT_1, orient_1])?;
+        Ok(())
+    }
+
+    pub fn set_flat(&mut self, config: FlatConfig) -> Result<(), Error<I2C::Error>> {
+        self.i2c.write(self.address, &[INT_FLAT_0, config.theta & 0x3F])?;
+        self.i2c.write(self.address, &[INT_FLAT_1, ((config.hold as u8) << 4) | (config.hysteresis & 0b111)])?;
+        Ok(())
+    }
+
+    /// Enables an interrupt engine and routes it to `pin`. Calling it again for the other pin
+    /// routes the interrupt to both.
+    pub fn enable_interrupt(&mut self, source: InterruptSource, pin: InterruptPin) -> Result<(), Error<I2C::Error>> {
+        let (register, bits) = source.enable_bits();
+        self.update_register(register, bits, bits)?;
+        let (register, bit) = source.map_bit(pin);
+        self.update_register(register, bit, bit)
+    }
+
+    /// Disables an interrupt engine and unmaps it from both pins.
+    pub fn disable_interrupt(&mut self, source: InterruptSource) -> Result<(), Error<I2C::Error>> {
+        let (register, bits) = source.enable_bits();
+        self.update_register(register, bits, 0)?;
+        for pin in [InterruptPin::Int1, InterruptPin::Int2] {
+            let (register, bit) = source.map_bit(pin);
+            self.update_register(register, bit, 0)?;
+        }
+        Ok(())
+    }
+
+    pub fn read_interrupt_status(&mut self) -> Result<InterruptStatus, Error<I2C::Error>> {
+        let mut status: [u8; 4] = [0; 4];
+        self.i2c.write_read(self.address, &[INT_STATUS_0], &mut status)?;
+        Ok(InterruptStatus(status))
+    }
+
+    /// Starts counting steps. The mode also applies to the step detector interrupt.
+    pub fn enable_step_counter(&mut self, mode: StepMode) -> Result<(), Error<I2C::Error>> {
+        let [step_conf_0, step_conf_1] = mode.step_conf();
+        self.i2c.write(self.address, &[STEP_CONF_0, step_conf_0])?;
+        self.i2c.write(self.address, &[STEP_CONF_1, step_conf_1 | STEP_CNT_EN])?;
+        Ok(())
+    }
+
+    /// Stops counting, the count is kept.
+    pub fn disable_step_counter(&mut self) -> Result<(), Error<I2C::Error>> {
+        self.update_register(STEP_CONF_1, STEP_CNT_EN, 0)
+    }
+
+    /// The sensor's step count, which wraps around after 65535.
+    pub fn read_step_count(&mut self) -> Result<u16, Error<I2C::Error>> {
+        let mut count: [u8; 2] = [0; 2];
+        self.i2c.write_read(self.address, &[STEP_CNT_0], &mut count)?;
+        Ok(u16::from_le_bytes(count))
+    }
+
+    /// Steps since the driver was created or the counter was reset. Call it at least once every
+    /// 65535 steps, so that no wrap-around of the sensor's count is missed.
+    pub fn step_total(&mut self) -> Result<u32, Error<I2C::Error>> {
+        let count = self.read_step_count()?;
+        self.step_total = self.step_total.wrapping_add(count.wrapping_sub(self.step_count) as u32);
+        self.step_count = count;
+        Ok(self.step_total)
+    }
+
+    /// Sets both the sensor's count and the total to 0.
+    pub fn reset_step_counter(&mut self) -> Result<(), Error<I2C::Error>> {
+        self.i2c.write(self.address, &[CMD, 0xB2])?;
+        self.step_count = 0;
+        self.step_total = 0;
+        Ok(())
+    }
+
+    /// Runs the sensor's fast offset compensation, which measures the offsets while the sensor
+    /// lies still in `pose` and then corrects all readings by them, including FIFO data. `gyro`
+    /// also compensates the gyroscope. The offsets are lost at power off unless saved with
+    /// `save_offsets`.
+    pub fn run_foc<F>(&mut self, pose: CalibrationPose, gyro: bool, delay_fn: F) -> Result<(), Error<I2C::Error>>
+    where F: Fn(u16) {
+        self.i2c.write(self.address, &[FOC_CONF, pose.foc_conf(gyro)])?;
+        self.i2c.write(self.address, &[CMD, 0x03])?;
+        self.wait_for_status(FOC_RDY, &delay_fn)?;
+        let enable = if gyro { ACC_OFF_EN | GYR_OFF_EN } else { ACC_OFF_EN };
+        self.update_register(OFFSET_6, enable, enable)
+    }
+
+    /// Writes the offset registers to the NVM, so that the sensor loads them at power on. The
+    /// NVM only takes a limited number of writes, so do this once per board.
+    pub fn save_offsets<F>(&mut self, delay_fn: F) -> Result<(), Error<I2C::Error>>
+    where F: Fn(u16) {
+        self.update_register(CONF, NVM_PROG_EN, NVM_PROG_EN)?;
+        self.i2c.write(self.address, &[CMD, 0xA0])?;
+        let result = self.wait_for_status(NVM_RDY, &delay_fn);
+        // Lock the NVM again, even if writing it timed out
+        self.update_register(CONF, NVM_PROG_EN, 0)?;
+        result
+    }
+
+    /// Averages `samples` readings while the sensor lies still in `pose`, one per accelerometer
+    /// sample period, and returns the bias of each axis. Pass it to `set_calibration` to
+    /// subtract it in `update`, and keep it, for example in the EEPROM, for the next start.
+    pub fn calibrate<F>(&mut self, pose: CalibrationPose, samples: u16, delay_fn: F) -> Result<CalibrationData, Error<I2C::Error>>
+    where F: Fn(u16) {
+        let samples = samples.max(1);
+        // Can't overflow, even with 65535 samples of the largest reading
+        let mut sums: [i32; 6] = [0; 6];
+        for _ in 0..samples {
+            delay_fn(self.config.accel_odr.period_ms());
+            let raw_data = self.read_raw()?;
+            for (sum, value) in sums.iter_mut().zip(raw_data) {
+                *sum += value as i32;
+            }
+        }
+        let average = |index: usize| sums[index] as f32 / samples as f32;
+        let gravity = pose.gravity();
+        Ok(CalibrationData {
+            accel_bias: [0, 1, 2].map(|axis| average(axis + 3) * self.a_res - gravity[axis]),
+            gyro_bias: [0, 1, 2].map(|axis| average(axis) * self.g_res),
+        })
+    }
+
+    pub fn set_calibration(&mut self, calibration: Option<CalibrationData>) {
+        self.calibration = calibration;
+    }
+
+    /// Polls STATUS until one of `bits` is set.
+    fn wait_for_status<F>(&mut self, bits: u8, delay_fn: &F) -> Result<(), Error<I2C::Error>>
+    where F: Fn(u16) {
+        for _ in 0..STATUS_POLLS {
+            delay_fn(10);
+            let mut status: [u8; 1] = [0];
+            self.i2c.write_read(self.address, &[STATUS], &mut status)?;
+            if status[0] & bits != 0 {
+                return Ok(());
+            }
+        }
+        Err(Error::Timeout)
+    }
+
+    /// Sets the bits of `mask` in `register` to those of `value`, leaving the others.
+    fn update_register(&mut self, register: u8, mask: u8, value: u8) -> Result<(), Error<I2C::Error>> {
+        let mut current: [u8; 1] = [0];
+        self.i2c.write_read(self.address, &[register], &mut current)?;
+        self.i2c.write(self.address, &[register, (current[0] & !mask) | (value & mask)])?;
+        Ok(())
+    }
+
+    /// The accelerometer range in g.
+    fn accel_range(&self) -> f32 {
+        self.config.accel_range.resolution() * 32768.0
+    }
+
+    /// Gyroscope x, y, z and accelerometer x, y, z, unscaled.
+    fn read_raw(&mut self) -> Result<[i16; 6], Error<I2C::Error>> {
         let mut raw_data: [u8; 12] = [0; 12];
         let mut signed_data: [i16; 6] = [0; 6];
 
@@ -94,6 +398,11 @@ impl<I2C: I2c> Driver<I2C> {
         for i in 0..6 {
             signed_data[i] = (((raw_data[i*2 + 1] as u16) << 8) | raw_data[i*2] as u16) as i16;
         }
+        Ok(signed_data)
+    }
+
+    pub fn update(&mut self) -> Result<(), Error<I2C::Error>>{
+        let signed_data = self.read_raw()?;
         let ax = (signed_data[3] as f32) * self.a_res;
         let ay = (signed_data[4] as f32) * self.a_res;
         let az = (signed_data[5] as f32) * self.a_res;
@@ -144,4 +453,219 @@ impl<I2C: I2c> Driver<I2C> {
     pub fn get_output_data(&self) -> &Option<OutputData> {
         &self.output_data
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bmi160::{CalibrationData, Driver, Vector};
+    use core::cell::Cell;
+    use crate::bmi160_config::{AccelOdr, AccelRange, AxisTarget, Bandwidth, CalibrationPose, Config, GyroOdr, GyroRange, StepMode};
+    use crate::bmi160_error::Error;
+    use crate::bmi160_fifo::{FifoConfig, FifoFrame};
+    use crate::bmi160_interrupt::*;
+    use crate::bmi160_registers::*;
+    use crate::test_support::RegisterI2c;
+
+    fn sensor() -> RegisterI2c {
+        let mut i2c = RegisterI2c::new();
+        i2c.registers[CHIP_ID as usize] = CHIP_ID_DEFAULT_VALUE;
+        i2c
+    }
+
+    #[test]
+    fn config_test() {
+        let config = Config {
+            accel_range: AccelRange::G2,
+            accel_odr: AccelOdr::Hz25,
+            accel_bandwidth: Bandwidth::Normal,
+            gyro_range: GyroRange::Dps250,
+            ..Config::default()
+        };
+        let mut driver = Driver::new(sensor(), None, None, config, |_| {}).unwrap();
+        assert_eq!(driver.i2c.registers[ACC_RANGE as usize], 0x03);
+        assert_eq!(driver.i2c.registers[ACC_CONF as usize], 0x26);
+        assert_eq!(driver.i2c.registers[GYR_RANGE as usize], 0x03);
+        assert_eq!(driver.i2c.registers[GYR_CONF as usize], 0x0A);
+
+        // 16384 LSB is 1 g at +-2 g and 8 g at +-16 g, 8192 LSB is 62.5 dps at +-250 dps
+        driver.i2c.registers[ACC_X_H as usize] = 0x40;
+        driver.i2c.registers[GYR_Z_H as usize] = 0x20;
+        driver.update().unwrap();
+        let output = driver.get_output_data().clone().unwrap();
+        assert_eq!((output.acceleration.x, output.gyro.z), (1.0, 62.5));
+
+        driver.set_accel_range(AccelRange::G16).unwrap();
+        driver.set_gyro_range(GyroRange::Dps2000).unwrap();
+        driver.update().unwrap();
+        let output = driver.get_output_data().clone().unwrap();
+        assert_eq!((output.acceleration.x, output.gyro.z), (8.0, 500.0));
+
+        driver.set_accel_rate(AccelOdr::Hz1600, Bandwidth::Osr2).unwrap();
+        driver.set_gyro_rate(GyroOdr::Hz25, Bandwidth::Osr4).unwrap();
+        assert_eq!(driver.i2c.registers[ACC_CONF as usize], 0x1C);
+        assert_eq!(driver.i2c.registers[GYR_CONF as usize], 0x06);
+        assert_eq!(driver.config().accel_range, AccelRange::G16);
+        assert_eq!(driver.config().gyro_odr, GyroOdr::Hz25);
+    }
+
+    #[test]
+    fn fifo_test() {
+        let calibration = CalibrationData { accel_bias: [0.5, 0.0, 0.0], gyro_bias: [0.0; 3] };
+        let mut driver = Driver::new(sensor(), None, Some(calibration), Config::default(), |_| {}).unwrap();
+        driver.configure_fifo(FifoConfig { accel: true, gyro: true, sensortime: true, watermark: 100, ..FifoConfig::default() }).unwrap();
+        assert_eq!(&driver.i2c.registers[FIFO_CONFIG_0 as usize..=FIFO_CONFIG_1 as usize], &[25, 0xD2]);
+        driver.flush_fifo().unwrap();
+        assert_eq!(driver.i2c.registers[CMD as usize], 0xB0);
+
+        // At +-16 g and +-2000 dps, 2048 LSB is 1 g and 1024 LSB is 62.5 dps
+        let data = [
+            0x8C, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00,
+            0x85, 0x00, 0x00, 0x00, 0xF8, 0x00, 0x00,
+            0x40, 0x03,
+            0x48, 0x01,
+            0x44, 0x01, 0x02, 0x03,
+            0x8C, 0x00, 0x04,
+        ];
+        driver.i2c.fifo.extend(data);
+        driver.i2c.registers[FIFO_LENGTH_0 as usize] = data.len() as u8;
+        driver.i2c.registers[FIFO_LENGTH_1 as usize] = 0xF8;
+        let mut buffer = [0x00; 64];
+        let frames: Vec<FifoFrame> = driver.read_fifo(&mut buffer).unwrap().collect();
+        assert_eq!(frames, [
+            FifoFrame::Sample {
+                acceleration: Some(Vector { x: 0.5, y: 0.0, z: 0.0 }),
+                gyro: Some(Vector { x: 62.5, y: 0.0, z: 0.0 }),
+            },
+            FifoFrame::Sample { acceleration: Some(Vector { x: -0.5, y: -1.0, z: 0.0 }), gyro: None },
+            FifoFrame::Skip(3),
+            FifoFrame::ConfigChange(0x01),
+            FifoFrame::SensorTime(0x030201),
+        ]);
+        assert!(driver.i2c.fifo.is_empty());
+
+        // Only what fits in the buffer is read
+        driver.i2c.fifo.extend(data);
+        let mut buffer = [0x00; 16];
+        assert_eq!(driver.read_fifo(&mut buffer).unwrap().count(), 1);
+        assert_eq!(driver.i2c.fifo.len(), data.len() - 16);
+    }
+
+    #[test]
+    fn interrupt_test() {
+        let mut driver = Driver::new(sensor(), None, None, Config::default(), |_| {}).unwrap();
+        driver.configure_interrupt_pin(InterruptPin::Int1, PinConfig { active_high: false, open_drain: true }).unwrap();
+        driver.configure_interrupt_pin(InterruptPin::Int2, PinConfig::default()).unwrap();
+        driver.set_interrupt_latch(Latch::Latched).unwrap();
+        assert_eq!(driver.i2c.registers[INT_OUT_CTRL as usize], 0xAC);
+        assert_eq!(driver.i2c.registers[INT_LATCH as usize], 0x0F);
+
+        // 0.5 g is 16 steps of 31.25 mg at +-16 g, 2 g is 4 steps of 500 mg
+        driver.set_any_motion(AnyMotionConfig { threshold: 0.5, samples: 2 }).unwrap();
+        driver.set_no_motion(NoMotionConfig { threshold: 0.25, duration: 30.0 }).unwrap();
+        driver.set_tap(TapConfig { threshold: 2.0, window: TapWindow::Ms250, long_shock: false, short_quiet: true }).unwrap();
+        assert_eq!(&driver.i2c.registers[INT_MOTION_0 as usize..=INT_MOTION_3 as usize], &[0x45, 16, 8, 0x01]);
+        assert_eq!(&driver.i2c.registers[INT_TAP_0 as usize..=INT_TAP_1 as usize], &[0x84, 4]);
+        // Changing the range keeps the thresholds in g
+        driver.set_accel_range(AccelRange::G4).unwrap();
+        assert_eq!(&driver.i2c.registers[INT_MOTION_0 as usize..=INT_MOTION_3 as usize], &[0x45, 64, 32, 0x01]);
+        assert_eq!(&driver.i2c.registers[INT_TAP_0 as usize..=INT_TAP_1 as usize], &[0x84, 16]);
+        driver.set_orientation(OrientationConfig::default()).unwrap();
+        driver.set_flat(FlatConfig::default()).unwrap();
+        assert_eq!(&driver.i2c.registers[INT_ORIENT_0 as usize..=INT_FLAT_1 as usize], &[0x18, 0x48, 0x08, 0x14]);
+
+        driver.enable_interrupt(InterruptSource::AnyMotion, InterruptPin::Int1).unwrap();
+        driver.enable_interrupt(InterruptSource::DoubleTap, InterruptPin::Int1).unwrap();
+        driver.enable_interrupt(InterruptSource::DoubleTap, InterruptPin::Int2).unwrap();
+        driver.enable_interrupt(InterruptSource::DataReady, InterruptPin::Int2).unwrap();
+        assert_eq!(&driver.i2c.registers[INT_EN_0 as usize..=INT_EN_2 as usize], &[0x17, 0x10, 0x00]);
+        assert_eq!(&driver.i2c.registers[INT_MAP_0 as usize..=INT_MAP_2 as usize], &[0x14, 0x08, 0x10]);
+        driver.disable_interrupt(InterruptSource::DoubleTap).unwrap();
+        assert_eq!(driver.i2c.registers[INT_EN_0 as usize], 0x07);
+        assert_eq!(&driver.i2c.registers[INT_MAP_0 as usize..=INT_MAP_2 as usize], &[0x04, 0x08, 0x00]);
+
+        // Any-motion first on z going down, double tap first on x, data ready, and the
+        // orientation is portrait upside down facing down
+        driver.i2c.registers[INT_STATUS_0 as usize..=INT_STATUS_3 as usize].copy_from_slice(&[0x54, 0x10, 0x1C, 0x50]);
+        let status = driver.read_interrupt_status().unwrap();
+        let events: Vec<InterruptEvent> = status.events().collect();
+        assert_eq!(events, [
+            InterruptEvent::DataReady,
+            InterruptEvent::AnyMotion { axis: Some(Axis::Z), negative: true },
+            InterruptEvent::DoubleTap { axis: Some(Axis::X), negative: false },
+            InterruptEvent::Orientation { orientation: Orientation::PortraitUpsideDown, face_down: true },
+        ]);
+        driver.reset_interrupts().unwrap();
+        assert_eq!(driver.i2c.registers[CMD as usize], 0xB1);
+    }
+
+    #[test]
+    fn step_counter_test() {
+        let mut driver = Driver::new(sensor(), None, None, Config::default(), |_| {}).unwrap();
+        driver.enable_step_counter(StepMode::Robust).unwrap();
+        assert_eq!(&driver.i2c.registers[STEP_CONF_0 as usize..=STEP_CONF_1 as usize], &[0x1D, 0x0F]);
+        driver.disable_step_counter().unwrap();
+        assert_eq!(driver.i2c.registers[STEP_CONF_1 as usize], 0x07);
+
+        driver.i2c.registers[STEP_CNT_0 as usize..=STEP_CNT_1 as usize].copy_from_slice(&[0xF0, 0xFF]);
+        assert_eq!(driver.read_step_count().unwrap(), 0xFFF0);
+        assert_eq!(driver.step_total().unwrap(), 0xFFF0);
+        // The sensor's count wrapped around
+        driver.i2c.registers[STEP_CNT_0 as usize..=STEP_CNT_1 as usize].copy_from_slice(&[0x10, 0x00]);
+        assert_eq!(driver.step_total().unwrap(), 0x10010);
+        assert_eq!(driver.step_total().unwrap(), 0x10010);
+
+        driver.reset_step_counter().unwrap();
+        assert_eq!(driver.i2c.registers[CMD as usize], 0xB2);
+        driver.i2c.registers[STEP_CNT_0 as usize..=STEP_CNT_1 as usize].copy_from_slice(&[0x03, 0x00]);
+        assert_eq!(driver.step_total().unwrap(), 3);
+
+        driver.enable_interrupt(InterruptSource::StepDetector, InterruptPin::Int2).unwrap();
+        assert_eq!(driver.i2c.registers[INT_EN_2 as usize], 0x08);
+        assert_eq!(driver.i2c.registers[INT_MAP_2 as usize], 0x01);
+        driver.i2c.registers[INT_STATUS_0 as usize] = 0x01;
+        let events: Vec<InterruptEvent> = driver.read_interrupt_status().unwrap().events().collect();
+        assert_eq!(events, [InterruptEvent::StepDetected]);
+    }
+
+    #[test]
+    fn foc_test() {
+        let mut driver = Driver::new(sensor(), None, None, Config::default(), |_| {}).unwrap();
+        let delay = Cell::new(0);
+        let delay_fn = |ms: u16| delay.set(delay.get() + ms as u32);
+        // Neither ready bit set
+        assert!(matches!(driver.run_foc(CalibrationPose::FLAT, true, delay_fn), Err(Error::Timeout)));
+        assert_eq!(delay.get(), 500);
+        assert_eq!(driver.i2c.registers[FOC_CONF as usize], 0x7D);
+        assert_eq!(driver.i2c.registers[CMD as usize], 0x03);
+
+        driver.i2c.registers[STATUS as usize] = 0x18;
+        let pose = CalibrationPose { x: AxisTarget::MinusOneG, ..CalibrationPose::FLAT };
+        driver.run_foc(pose, false, delay_fn).unwrap();
+        assert_eq!(driver.i2c.registers[FOC_CONF as usize], 0x2D);
+        assert_eq!(driver.i2c.registers[OFFSET_6 as usize], 0x40);
+
+        driver.save_offsets(delay_fn).unwrap();
+        assert_eq!(driver.i2c.registers[CMD as usize], 0xA0);
+        assert_eq!(driver.i2c.registers[CONF as usize], 0x00);
+    }
+
+    #[test]
+    fn calibrate_test() {
+        let mut driver = Driver::new(sensor(), None, None, Config::default(), |_| {}).unwrap();
+        // About 1.05 g on z at +-16 g, and 1.95 dps on the gyroscope's x axis at +-2000 dps
+        driver.i2c.registers[ACC_Z_L as usize..=ACC_Z_H as usize].copy_from_slice(&2150i16.to_le_bytes());
+        driver.i2c.registers[GYR_X_L as usize..=GYR_X_H as usize].copy_from_slice(&32i16.to_le_bytes());
+        let delay = Cell::new(0);
+        let calibration = driver.calibrate(CalibrationPose::FLAT, 16, |ms| delay.set(delay.get() + ms)).unwrap();
+        // One 400 Hz sample period for each sample
+        assert_eq!(delay.get(), 16 * 3);
+        assert_eq!(calibration.accel_bias, [0.0, 0.0, 102.0 / 2048.0]);
+        assert_eq!(calibration.gyro_bias, [1.953125, 0.0, 0.0]);
+
+        driver.set_calibration(Some(calibration));
+        driver.update().unwrap();
+        let output = driver.get_output_data().clone().unwrap();
+        assert_eq!(output.acceleration, Vector { x: 0.0, y: 0.0, z: 1.0 });
+        assert_eq!(output.gyro, Vector { x: 0.0, y: 0.0, z: 0.0 });
+    }
+}
@@ -0,0 +1,197 @@
+/// Accelerometer full scale range. The discriminants are the ACC_RANGE register values.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AccelRange {
+    G2 = 0x03,
+    G4 = 0x05,
+    G8 = 0x08,
+    G16 = 0x0C,
+}
+
+impl AccelRange {
+    /// g per LSB of the raw readings.
+    pub fn resolution(self) -> f32 {
+        let range = match self {
+            AccelRange::G2 => 2.0,
+            AccelRange::G4 => 4.0,
+            AccelRange::G8 => 8.0,
+            AccelRange::G16 => 16.0,
+        };
+        range / 32768.0
+    }
+}
+
+/// Gyroscope full scale range in degrees per second. The discriminants are the GYR_RANGE
+/// register values.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GyroRange {
+    Dps2000 = 0x00,
+    Dps1000 = 0x01,
+    Dps500 = 0x02,
+    Dps250 = 0x03,
+    Dps125 = 0x04,
+}
+
+impl GyroRange {
+    /// Degrees per second per LSB of the raw readings.
+    pub fn resolution(self) -> f32 {
+        let range = match self {
+            GyroRange::Dps2000 => 2000.0,
+            GyroRange::Dps1000 => 1000.0,
+            GyroRange::Dps500 => 500.0,
+            GyroRange::Dps250 => 250.0,
+            GyroRange::Dps125 => 125.0,
+        };
+        range / 32768.0
+    }
+}
+
+/// Accelerometer output data rate. Rates below 12.5 Hz need the undersampling mode, which isn't
+/// supported.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AccelOdr {
+    Hz12_5 = 0x05,
+    Hz25 = 0x06,
+    Hz50 = 0x07,
+    Hz100 = 0x08,
+    Hz200 = 0x09,
+    Hz400 = 0x0A,
+    Hz800 = 0x0B,
+    Hz1600 = 0x0C,
+}
+
+impl AccelOdr {
+    /// Time between samples, rounded up to whole milliseconds.
+    pub fn period_ms(self) -> u16 {
+        match self {
+            AccelOdr::Hz12_5 => 80,
+            AccelOdr::Hz25 => 40,
+            AccelOdr::Hz50 => 20,
+            AccelOdr::Hz100 => 10,
+            AccelOdr::Hz200 => 5,
+            AccelOdr::Hz400 => 3,
+            AccelOdr::Hz800 | AccelOdr::Hz1600 => 1,
+        }
+    }
+}
+
+/// Gyroscope output data rate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GyroOdr {
+    Hz25 = 0x06,
+    Hz50 = 0x07,
+    Hz100 = 0x08,
+    Hz200 = 0x09,
+    Hz400 = 0x0A,
+    Hz800 = 0x0B,
+    Hz1600 = 0x0C,
+    Hz3200 = 0x0D,
+}
+
+/// Low pass filter setting. With 4 or 2 times oversampling the 3 dB cutoff is about a quarter or
+/// a half of what it is in `Normal` mode, at the same output data rate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Bandwidth {
+    Osr4 = 0b00,
+    Osr2 = 0b01,
+    Normal = 0b10,
+}
+
+/// Step detection settings, trading missed steps against false ones.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StepMode {
+    Normal,
+    /// Counts light steps too, for example of small people
+    Sensitive,
+    /// Fewer false steps from other movement
+    Robust,
+}
+
+impl StepMode {
+    /// STEP_CONF_0 and STEP_CONF_1 values, with the counter disabled. These are the settings
+    /// recommended by Bosch.
+    pub fn step_conf(self) -> [u8; 2] {
+        match self {
+            StepMode::Normal => [0x15, 0x03],
+            StepMode::Sensitive => [0x2D, 0x00],
+            StepMode::Robust => [0x1D, 0x07],
+        }
+    }
+}
+
+/// What an accelerometer axis reads while calibrating. The discriminants are the FOC_CONF values.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AxisTarget {
+    PlusOneG = 0b01,
+    MinusOneG = 0b10,
+    Zero = 0b11,
+}
+
+impl AxisTarget {
+    fn g(self) -> f32 {
+        match self {
+            AxisTarget::PlusOneG => 1.0,
+            AxisTarget::MinusOneG => -1.0,
+            AxisTarget::Zero => 0.0,
+        }
+    }
+}
+
+/// How the sensor lies while calibrating, as the reading of each axis.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CalibrationPose {
+    pub x: AxisTarget,
+    pub y: AxisTarget,
+    pub z: AxisTarget,
+}
+
+impl CalibrationPose {
+    /// Lying flat, face up.
+    pub const FLAT: Self = Self { x: AxisTarget::Zero, y: AxisTarget::Zero, z: AxisTarget::PlusOneG };
+
+    /// FOC_CONF value, `gyro` also compensates the gyroscope.
+    pub fn foc_conf(&self, gyro: bool) -> u8 {
+        ((gyro as u8) << 6) | ((self.x as u8) << 4) | ((self.y as u8) << 2) | self.z as u8
+    }
+
+    /// The expected acceleration in g.
+    pub fn gravity(&self) -> [f32; 3] {
+        [self.x.g(), self.y.g(), self.z.g()]
+    }
+}
+
+/// Sensor settings written by `Driver::new`. The default is ±16 g and ±2000 dps at 400 Hz with
+/// 4 times oversampling.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Config {
+    pub accel_range: AccelRange,
+    pub accel_odr: AccelOdr,
+    pub accel_bandwidth: Bandwidth,
+    pub gyro_range: GyroRange,
+    pub gyro_odr: GyroOdr,
+    pub gyro_bandwidth: Bandwidth,
+}
+
+impl Config {
+    /// ACC_CONF value, with undersampling off.
+    pub fn acc_conf(&self) -> u8 {
+        ((self.accel_bandwidth as u8) << 4) | self.accel_odr as u8
+    }
+
+    /// GYR_CONF value.
+    pub fn gyr_conf(&self) -> u8 {
+        ((self.gyro_bandwidth as u8) << 4) | self.gyro_odr as u8
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            accel_range: AccelRange::G16,
+            accel_odr: AccelOdr::Hz400,
+            accel_bandwidth: Bandwidth::Osr4,
+            gyro_range: GyroRange::Dps2000,
+            gyro_odr: GyroOdr::Hz400,
+            gyro_bandwidth: Bandwidth::Osr4,
+        }
+    }
+}
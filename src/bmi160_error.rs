@@ -1,8 +1,11 @@
 use ufmt::{Formatter, uWrite};
 
+#[derive(Debug)]
 pub enum Error<I2CError> {
     WrongChipId(u8),
     I2cError(I2CError),
+    /// The sensor didn't finish a command in time
+    Timeout,
 }
 
 impl<I2CError> From<I2CError> for Error<I2CError>
@@ -21,6 +24,9 @@ impl<I2CError> ufmt::uDisplay for Error<I2CError> {
             Error::I2cError(error) => {
                 fmt.write_str("i2c error")
             }
+            Error::Timeout => {
+                fmt.write_str("timeout")
+            }
         }
     }
 }
\ No newline at end of file
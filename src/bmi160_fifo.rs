@@ -0,0 +1,196 @@
+use crate::bmi160::{CalibrationData, Vector};
+
+/// Capacity of the FIFO in bytes.
+pub const FIFO_SIZE: usize = 1024;
+
+const HEADER_MODE_MASK: u8 = 0b1100_0000;
+const HEADER_REGULAR: u8 = 0b1000_0000;
+const HEADER_MAG: u8 = 0b0001_0000;
+const HEADER_GYRO: u8 = 0b0000_1000;
+const HEADER_ACCEL: u8 = 0b0000_0100;
+const HEADER_SKIP: u8 = 0x40;
+const HEADER_SENSORTIME: u8 = 0x44;
+const HEADER_INPUT_CONFIG: u8 = 0x48;
+/// Returned when reading past the end of the FIFO data
+const HEADER_EMPTY: u8 = 0x80;
+
+/// What the sensor stores in its FIFO, written by `Driver::configure_fifo`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FifoConfig {
+    /// Puts a header byte in front of every frame. Needed for the sensortime, skip and config
+    /// change frames, and when the sensors run at different data rates.
+    pub header: bool,
+    pub accel: bool,
+    pub gyro: bool,
+    /// Appends a sensortime frame when all data has been read, in header mode only.
+    pub sensortime: bool,
+    /// Fill level in bytes for the FIFO watermark interrupt, rounded down to a multiple of 4.
+    pub watermark: u16,
+}
+
+impl FifoConfig {
+    /// FIFO_CONFIG_0 value, the watermark in units of 4 bytes.
+    pub fn config_0(&self) -> u8 {
+        (self.watermark / 4).min(0xFF) as u8
+    }
+
+    /// FIFO_CONFIG_1 value.
+    pub fn config_1(&self) -> u8 {
+        ((self.gyro as u8) << 7) | ((self.accel as u8) << 6) | ((self.header as u8) << 4) | ((self.sensortime as u8) << 1)
+    }
+
+    /// Bytes per frame in headerless mode.
+    fn frame_length(&self) -> usize {
+        6 * (self.accel as usize + self.gyro as usize)
+    }
+}
+
+impl Default for FifoConfig {
+    /// The sensor's reset state: header mode with no sensors, so the FIFO stays empty.
+    fn default() -> Self {
+        Self {
+            header: true,
+            accel: false,
+            gyro: false,
+            sensortime: false,
+            watermark: 0,
+        }
+    }
+}
+
+/// One frame of FIFO data.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FifoFrame {
+    /// Readings in g and degrees per second, like `OutputData`. Only the sensors in the frame
+    /// are set.
+    Sample {
+        acceleration: Option<Vector>,
+        gyro: Option<Vector>,
+    },
+    /// This many frames were dropped because the FIFO was full.
+    Skip(u8),
+    /// The 24 bit sensortime, in units of 39.0625 us, when the last frame was read.
+    SensorTime(u32),
+    /// A sensor's rate or range changed, the raw payload says which. The following samples
+    /// use the new settings.
+    ConfigChange(u8),
+}
+
+/// Parses FIFO data into frames, see `Driver::read_fifo`. Stops at the end of the data, at a
+/// frame that was only read partially, or at an unknown header.
+pub struct FifoFrames<'a> {
+    data: &'a [u8],
+    config: FifoConfig,
+    a_res: f32,
+    g_res: f32,
+    calibration: Option<CalibrationData>,
+}
+
+impl<'a> FifoFrames<'a> {
+    /// `a_res` and `g_res` scale the raw readings, like in `Driver::update`.
+    pub fn new(data: &'a [u8], config: FifoConfig, a_res: f32, g_res: f32, calibration: Option<CalibrationData>) -> Self {
+        Self { data, config, a_res, g_res, calibration }
+    }
+
+    /// Takes `length` bytes off the front of the data, or ends the parsing if there aren't enough.
+    fn take(&mut self, length: usize) -> Option<&'a [u8]> {
+        if self.data.len() < length {
+            self.data = &[];
+            return None;
+        }
+        let (bytes, rest) = self.data.split_at(length);
+        self.data = rest;
+        Some(bytes)
+    }
+
+    fn sample(&mut self, mag: bool, gyro: bool, accel: bool) -> Option<FifoFrame> {
+        let length = 8 * mag as usize + 6 * (gyro as usize + accel as usize);
+        let bytes = self.take(length)?;
+        // The magnetometer isn't supported, so its data is skipped
+        let bytes = &bytes[8 * mag as usize..];
+        let (gyro_bytes, accel_bytes) = bytes.split_at(6 * gyro as usize);
+        let (accel_bias, gyro_bias) = match &self.calibration {
+            Some(calibration) => (calibration.accel_bias, calibration.gyro_bias),
+            None => ([0.0; 3], [0.0; 3]),
+        };
+        Some(FifoFrame::Sample {
+            acceleration: accel.then(|| vector(accel_bytes, self.a_res, accel_bias)),
+            gyro: gyro.then(|| vector(gyro_bytes, self.g_res, gyro_bias)),
+        })
+    }
+}
+
+impl Iterator for FifoFrames<'_> {
+    type Item = FifoFrame;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.config.header {
+            let length = self.config.frame_length();
+            if length == 0 {
+                return None;
+            }
+            return self.sample(false, self.config.gyro, self.config.accel);
+        }
+        let header = *self.take(1)?.first()?;
+        // The low two bits are interrupt tags
+        match header & !0b11 {
+            HEADER_EMPTY => {
+                self.data = &[];
+                None
+            }
+            regular if regular & HEADER_MODE_MASK == HEADER_REGULAR => {
+                self.sample(regular & HEADER_MAG != 0, regular & HEADER_GYRO != 0, regular & HEADER_ACCEL != 0)
+            }
+            HEADER_SKIP => Some(FifoFrame::Skip(self.take(1)?[0])),
+            HEADER_SENSORTIME => {
+                let bytes = self.take(3)?;
+                Some(FifoFrame::SensorTime(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0])))
+            }
+            HEADER_INPUT_CONFIG => Some(FifoFrame::ConfigChange(self.take(1)?[0])),
+            _ => {
+                // The frame length is unknown, so nothing after it can be parsed
+                self.data = &[];
+                None
+            }
+        }
+    }
+}
+
+/// Scales three little endian 16 bit readings and subtracts the bias.
+fn vector(bytes: &[u8], resolution: f32, bias: [f32; 3]) -> Vector {
+    let axis = |index: usize| {
+        let raw = i16::from_le_bytes([bytes[index * 2], bytes[index * 2 + 1]]);
+        raw as f32 * resolution - bias[index]
+    };
+    Vector {
+        x: axis(0),
+        y: axis(1),
+        z: axis(2),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bmi160::Vector;
+    use crate::bmi160_fifo::{FifoConfig, FifoFrame, FifoFrames};
+
+    #[test]
+    fn config_test() {
+        let config = FifoConfig { accel: true, gyro: true, sensortime: true, watermark: 102, ..FifoConfig::default() };
+        assert_eq!((config.config_0(), config.config_1()), (25, 0xD2));
+        let config = FifoConfig { header: false, accel: true, watermark: 4000, ..FifoConfig::default() };
+        assert_eq!((config.config_0(), config.config_1()), (0xFF, 0x40));
+    }
+
+    #[test]
+    fn headerless_test() {
+        let config = FifoConfig { header: false, accel: true, ..FifoConfig::default() };
+        // Two accelerometer frames and the start of a third
+        let data = [0x00, 0x01, 0x00, 0x00, 0x00, 0xFF, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01];
+        let frames: Vec<FifoFrame> = FifoFrames::new(&data, config, 1.0 / 256.0, 1.0, None).collect();
+        assert_eq!(frames, [
+            FifoFrame::Sample { acceleration: Some(Vector { x: 1.0, y: 0.0, z: -1.0 }), gyro: None },
+            FifoFrame::Sample { acceleration: Some(Vector { x: 2.0, y: 0.0, z: 0.0 }), gyro: None },
+        ]);
+    }
+}
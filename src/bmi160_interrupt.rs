@@ -0,0 +1,351 @@
+use crate::bmi160_registers::{INT_EN_0, INT_EN_1, INT_EN_2, INT_MAP_0, INT_MAP_1, INT_MAP_2};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InterruptPin {
+    Int1,
+    Int2,
+}
+
+/// Electrical behaviour of an interrupt pin. The default is active high push-pull.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PinConfig {
+    /// Active low if false
+    pub active_high: bool,
+    /// Open drain if true, push-pull if false
+    pub open_drain: bool,
+}
+
+impl Default for PinConfig {
+    fn default() -> Self {
+        Self {
+            active_high: true,
+            open_drain: false,
+        }
+    }
+}
+
+impl PinConfig {
+    /// The pin's nibble of INT_OUT_CTRL, with the output enabled.
+    pub fn out_ctrl(&self) -> u8 {
+        0b1000 | ((self.open_drain as u8) << 2) | ((self.active_high as u8) << 1)
+    }
+}
+
+/// How long the pins stay active after an interrupt. Latched interrupts stay active until
+/// `Driver::reset_interrupts`. The discriminants are the INT_LATCH values.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Latch {
+    NonLatched = 0x00,
+    Us312_5 = 0x01,
+    Us625 = 0x02,
+    Ms1_25 = 0x03,
+    Ms2_5 = 0x04,
+    Ms5 = 0x05,
+    Ms10 = 0x06,
+    Ms20 = 0x07,
+    Ms40 = 0x08,
+    Ms80 = 0x09,
+    Ms160 = 0x0A,
+    Ms320 = 0x0B,
+    Ms640 = 0x0C,
+    S1_28 = 0x0D,
+    S2_56 = 0x0E,
+    Latched = 0x0F,
+}
+
+/// An interrupt engine that can be enabled and mapped to a pin.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InterruptSource {
+    DataReady,
+    AnyMotion,
+    NoMotion,
+    SingleTap,
+    DoubleTap,
+    Orientation,
+    Flat,
+    FifoFull,
+    FifoWatermark,
+    /// Fires on every step, with the settings of `Driver::enable_step_counter`
+    StepDetector,
+}
+
+impl InterruptSource {
+    /// The INT_EN register and bits that enable the engine.
+    pub fn enable_bits(self) -> (u8, u8) {
+        match self {
+            InterruptSource::DataReady => (INT_EN_1, 1 << 4),
+            // All three axes
+            InterruptSource::AnyMotion => (INT_EN_0, 0b111),
+            InterruptSource::NoMotion => (INT_EN_2, 0b111),
+            InterruptSource::SingleTap => (INT_EN_0, 1 << 5),
+            InterruptSource::DoubleTap => (INT_EN_0, 1 << 4),
+            InterruptSource::Orientation => (INT_EN_0, 1 << 6),
+            InterruptSource::Flat => (INT_EN_0, 1 << 7),
+            InterruptSource::FifoFull => (INT_EN_1, 1 << 5),
+            InterruptSource::FifoWatermark => (INT_EN_1, 1 << 6),
+            InterruptSource::StepDetector => (INT_EN_2, 1 << 3),
+        }
+    }
+
+    /// The INT_MAP register and bit that route the engine to `pin`.
+    pub fn map_bit(self, pin: InterruptPin) -> (u8, u8) {
+        // INT_MAP_1 holds the data and FIFO interrupts of both pins, INT1 in the high nibble
+        let data_bit = |bit: u8| match pin {
+            InterruptPin::Int1 => (INT_MAP_1, 1 << (bit + 4)),
+            InterruptPin::Int2 => (INT_MAP_1, 1 << bit),
+        };
+        // INT_MAP_0 and INT_MAP_2 have the same layout for INT1 and INT2
+        let engine_bit = |bit: u8| match pin {
+            InterruptPin::Int1 => (INT_MAP_0, 1 << bit),
+            InterruptPin::Int2 => (INT_MAP_2, 1 << bit),
+        };
+        match self {
+            InterruptSource::DataReady => data_bit(3),
+            InterruptSource::FifoWatermark => data_bit(2),
+            InterruptSource::FifoFull => data_bit(1),
+            // Shared with the low-g interrupt
+            InterruptSource::StepDetector => engine_bit(0),
+            InterruptSource::AnyMotion => engine_bit(2),
+            InterruptSource::NoMotion => engine_bit(3),
+            InterruptSource::DoubleTap => engine_bit(4),
+            InterruptSource::SingleTap => engine_bit(5),
+            InterruptSource::Orientation => engine_bit(6),
+            InterruptSource::Flat => engine_bit(7),
+        }
+    }
+}
+
+/// Fires when the slope between consecutive accelerometer samples exceeds the threshold.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AnyMotionConfig {
+    /// In g
+    pub threshold: f32,
+    /// Consecutive samples above the threshold, 1 to 4
+    pub samples: u8,
+}
+
+/// Fires when the slope stays below the threshold for the duration.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NoMotionConfig {
+    /// In g
+    pub threshold: f32,
+    /// In seconds, 1.28 s to 430 s. Rounded up to a step of 1.28 s, 5.12 s or 10.24 s
+    /// depending on the length.
+    pub duration: f32,
+}
+
+/// Single and double tap detection.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TapConfig {
+    /// In g
+    pub threshold: f32,
+    /// Longest time between the two taps of a double tap
+    pub window: TapWindow,
+    /// Waits 75 ms instead of 50 ms after a tap before looking for the next
+    pub long_shock: bool,
+    /// Needs 20 ms instead of 30 ms of quiet around a tap
+    pub short_quiet: bool,
+}
+
+/// The discriminants are the int_tap_dur values.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TapWindow {
+    Ms50 = 0b000,
+    Ms100 = 0b001,
+    Ms150 = 0b010,
+    Ms200 = 0b011,
+    Ms250 = 0b100,
+    Ms375 = 0b101,
+    Ms500 = 0b110,
+    Ms700 = 0b111,
+}
+
+/// Thresholds between portrait and landscape. The discriminants are the orient_mode values.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OrientationMode {
+    Symmetrical = 0b00,
+    HighAsymmetrical = 0b01,
+    LowAsymmetrical = 0b10,
+}
+
+/// Fires when the device turns between portrait, landscape and face up or down. The default is
+/// the sensor's reset state.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OrientationConfig {
+    pub mode: OrientationMode,
+    /// 0 to 3, how much motion keeps the orientation from changing
+    pub blocking: u8,
+    /// 0 to 15, in steps of 62.5 mg
+    pub hysteresis: u8,
+    /// 0 to 63, the largest tilt angle for a change, as 64 * tan²(angle)
+    pub theta: u8,
+    /// Also report changes between face up and face down
+    pub up_down: bool,
+    /// Uses the x axis instead of z for face up and down, for devices standing upright
+    pub swap_axes: bool,
+}
+
+impl Default for OrientationConfig {
+    fn default() -> Self {
+        Self {
+            mode: OrientationMode::Symmetrical,
+            blocking: 0b10,
+            hysteresis: 1,
+            theta: 8,
+            up_down: true,
+            swap_axes: false,
+        }
+    }
+}
+
+/// Time the device has to stay flat before the flat interrupt fires. The discriminants are the
+/// flat_hold_time values.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FlatHold {
+    Ms0 = 0b00,
+    Ms640 = 0b01,
+    Ms1280 = 0b10,
+    Ms2560 = 0b11,
+}
+
+/// Fires when the device is laid flat or picked up. The default is the sensor's reset state.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FlatConfig {
+    /// 0 to 63, the largest tilt angle that counts as flat, as 64 * tan²(angle)
+    pub theta: u8,
+    /// 0 to 7
+    pub hysteresis: u8,
+    pub hold: FlatHold,
+}
+
+impl Default for FlatConfig {
+    fn default() -> Self {
+        Self {
+            theta: 8,
+            hysteresis: 4,
+            hold: FlatHold::Ms640,
+        }
+    }
+}
+
+/// Converts a threshold in g to register units of `range` / `steps` g, for an accelerometer
+/// range of `range` g.
+pub fn threshold(threshold: f32, range: f32, steps: f32) -> u8 {
+    let value = threshold * steps / range + 0.5;
+    if value <= 0.0 {
+        0
+    } else if value >= 255.0 {
+        255
+    } else {
+        value as u8
+    }
+}
+
+/// The int_slo_no_mot_dur value for a no-motion duration in seconds.
+pub fn no_motion_duration(seconds: f32) -> u8 {
+    let steps = |step: f32| {
+        let steps = seconds / step;
+        let whole = steps as u8;
+        if steps > whole as f32 { whole.saturating_add(1) } else { whole }
+    };
+    if seconds <= 16.0 * 1.28 {
+        steps(1.28).max(1) - 1
+    } else if seconds <= 20.0 * 5.12 {
+        0b01_0000 | (steps(5.12) - 5)
+    } else {
+        0b10_0000 | (steps(10.24).min(42) - 11)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Orientation {
+    PortraitUpright = 0b00,
+    PortraitUpsideDown = 0b01,
+    LandscapeLeft = 0b10,
+    LandscapeRight = 0b11,
+}
+
+/// An interrupt reported in INT_STATUS. The axis is the one that triggered first, and
+/// `negative` whether it moved in the negative direction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InterruptEvent {
+    DataReady,
+    AnyMotion { axis: Option<Axis>, negative: bool },
+    NoMotion,
+    SingleTap { axis: Option<Axis>, negative: bool },
+    DoubleTap { axis: Option<Axis>, negative: bool },
+    Orientation { orientation: Orientation, face_down: bool },
+    /// Whether the device is flat now
+    Flat(bool),
+    FifoFull,
+    FifoWatermark,
+    StepDetected,
+}
+
+/// The INT_STATUS_0 to INT_STATUS_3 registers, read by `Driver::read_interrupt_status`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InterruptStatus(pub [u8; 4]);
+
+impl InterruptStatus {
+    /// The interrupts that fired.
+    pub fn events(&self) -> impl Iterator<Item=InterruptEvent> {
+        let [status_0, status_1, status_2, status_3] = self.0;
+        let set = |byte: u8, bit: u8| byte & (1 << bit) != 0;
+        let first_axis = |byte: u8| match byte & 0b111 {
+            0 => None,
+            axes if axes & 0b001 != 0 => Some(Axis::X),
+            axes if axes & 0b010 != 0 => Some(Axis::Y),
+            _ => Some(Axis::Z),
+        };
+        let any_motion = (first_axis(status_2), set(status_2, 3));
+        let tap = (first_axis(status_2 >> 4), set(status_2, 7));
+        let orientation = match (status_3 >> 4) & 0b11 {
+            0b00 => Orientation::PortraitUpright,
+            0b01 => Orientation::PortraitUpsideDown,
+            0b10 => Orientation::LandscapeLeft,
+            _ => Orientation::LandscapeRight,
+        };
+        [
+            set(status_1, 4).then_some(InterruptEvent::DataReady),
+            set(status_0, 2).then_some(InterruptEvent::AnyMotion { axis: any_motion.0, negative: any_motion.1 }),
+            set(status_1, 7).then_some(InterruptEvent::NoMotion),
+            set(status_0, 5).then_some(InterruptEvent::SingleTap { axis: tap.0, negative: tap.1 }),
+            set(status_0, 4).then_some(InterruptEvent::DoubleTap { axis: tap.0, negative: tap.1 }),
+            set(status_0, 6).then_some(InterruptEvent::Orientation { orientation, face_down: set(status_3, 6) }),
+            set(status_0, 7).then_some(InterruptEvent::Flat(set(status_3, 7))),
+            set(status_1, 5).then_some(InterruptEvent::FifoFull),
+            set(status_1, 6).then_some(InterruptEvent::FifoWatermark),
+            set(status_0, 0).then_some(InterruptEvent::StepDetected),
+        ].into_iter().flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bmi160_interrupt::{no_motion_duration, threshold};
+
+    #[test]
+    fn conversion_test() {
+        // Any-motion steps are 3.91 mg at +-2 g and 31.25 mg at +-16 g
+        assert_eq!(threshold(0.5, 16.0, 512.0), 16);
+        assert_eq!(threshold(0.02, 2.0, 512.0), 5);
+        assert_eq!(threshold(-1.0, 2.0, 512.0), 0);
+        assert_eq!(threshold(100.0, 2.0, 512.0), 255);
+
+        assert_eq!(no_motion_duration(0.0), 0);
+        assert_eq!(no_motion_duration(1.28), 0);
+        assert_eq!(no_motion_duration(2.0), 1);
+        assert_eq!(no_motion_duration(20.48), 15);
+        assert_eq!(no_motion_duration(30.0), 0x11);
+        assert_eq!(no_motion_duration(200.0), 0x29);
+        assert_eq!(no_motion_duration(1000.0), 0x3F);
+        assert_eq!(no_motion_duration(1.0e6), 0x3F);
+    }
+}
@@ -1,4 +1,6 @@
 pub const CMD: u8 = 0x7E;
+pub const STEP_CONF_1: u8 = 0x7B;
+pub const STEP_CONF_0: u8 = 0x7A;
 pub const STEP_CNT_1: u8 = 0x79;
 pub const STEP_CNT_0: u8 = 0x78;
 pub const OFFSET_6: u8 = 0x77;
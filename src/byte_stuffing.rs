@@ -1,4 +1,5 @@
-// use crate::byte_stuffing::ReaderState::*;
+#[cfg(test)]
+use crate::byte_stuffing::ReaderState::*;
 
 pub fn encode_iter(bytes: &[u8]) -> impl Iterator<Item=u8> + '_ {
     let mut state = Some(EncodeState::Begin(bytes));
@@ -52,116 +53,106 @@ impl<'a> EncodeState<'a> {
     }
 }
 
-// pub fn from_reader<R>(mut reader: R, message_buffer: &mut [u8]) -> Result<&[u8], DecoderError>
-//     where R: std::io::Read,
-// {
-//     let mut current_bytes = 0;
-//     let mut state = WaitingForStart;
-//     let mut ingest_buffer = [0x00; 1];
-//     loop {
-//         let read_result = reader.read( &mut ingest_buffer);
-//         match read_result {
-//             Err(error) => return Err(DecoderError::IoError(error)),
-//             Ok(0) => {
-//                 return Err(DecoderError::ReaderEndOfFile)
-//             }
-//             Ok(_) => {
-//                 let next = ingest_buffer[0];
-//                 #[cfg(test)]
-//                 println!("Byte {}, state {:?}", next, state);
-//                 match next {
-//                     START_BYTE => {
-//                         state = InsideMessage;
-//                         current_bytes = 0;
-//                     }
-//                     END_BYTE => {
-//                         match state {
-//                             WaitingForStart => {}
-//                             InsideMessage => {
-//                                 return Ok(&message_buffer[..current_bytes]);
-//                             }
-//                             InsideMessageEscaping => {
-//                                 state = WaitingForStart;
-//                                 #[cfg(test)]
-//                                 return Err(DecoderError::InvalidEscaped(next));
-//                             }
-//                         }
-//                     }
-//                     ESCAPE_BYTE => {
-//                         match state {
-//                             WaitingForStart => {}
-//                             InsideMessage => {
-//                                 state = InsideMessageEscaping;
-//                             }
-//                             InsideMessageEscaping => {
-//                                 state = WaitingForStart;
-//                                 #[cfg(test)]
-//                                 return Err(DecoderError::InvalidEscaped(next));
-//                             }
-//                         }
-//                     }
-//                     _ => {
-//                         let byte = match state {
-//                             WaitingForStart => {continue}
-//                             InsideMessage => next,
-//                             InsideMessageEscaping => {
-//                                 match next ^ 0xFF {
-//                                     byte @ START_BYTE |
-//                                     byte @ END_BYTE |
-//                                     byte @ ESCAPE_BYTE => {
-//                                         byte
-//                                     }
-//                                     byte => {
-//                                         // We got an escaped character that didn't need escaping
-//                                         // We're probably getting invalid data.
-//                                         // Silently recover
-//                                         state = WaitingForStart;
-//                                         #[cfg(test)]
-//                                         return Err(DecoderError::InvalidEscaped(next));
-//                                         continue;
-//                                     },
-//                                 }
-//                             }
-//                         };
-//                         if let Some(slot) = message_buffer.get_mut(current_bytes) {
-//                             *slot = byte;
-//                             current_bytes += 1;
-//                             state = InsideMessage;
-//                         } else {
-//                             // Writing outside memory.
-//                             // We're getting longer messages than we should.
-//                             // Start over and hope that it recovers later
-//                             state = WaitingForStart;
-//                             #[cfg(test)]
-//                             return Err(DecoderError::BufferTooSmall);
-//                         }
-//                     }
-//                 }
-//             }
-//         }
-//     }
-// }
-//
-// #[derive(Debug)]
-// pub enum DecoderError {
-//     IoError(std::io::Error),
-//     ReaderEndOfFile,
-//     #[cfg(test)]
-//     BufferTooSmall,
-//     #[cfg(test)]
-//     InvalidEscaped(u8),
-// }
-//
-// #[derive(Debug, Clone)]
-// enum ReaderState {
-//     WaitingForStart,
-//     InsideMessage,
-//     InsideMessageEscaping,
-// }
+/// Reads one message from `reader`. Only built for the tests, since it needs std and fails on
+/// malformed input instead of recovering from it.
+#[cfg(test)]
+pub fn from_reader<R>(mut reader: R, message_buffer: &mut [u8]) -> Result<&[u8], DecoderError>
+    where R: std::io::Read,
+{
+    let mut current_bytes = 0;
+    let mut state = WaitingForStart;
+    let mut ingest_buffer = [0x00; 1];
+    loop {
+        let read_result = reader.read( &mut ingest_buffer);
+        match read_result {
+            Err(error) => return Err(DecoderError::IoError(error)),
+            Ok(0) => {
+                return Err(DecoderError::ReaderEndOfFile)
+            }
+            Ok(_) => {
+                let next = ingest_buffer[0];
+                match next {
+                    START_BYTE => {
+                        state = InsideMessage;
+                        current_bytes = 0;
+                    }
+                    END_BYTE => {
+                        match state {
+                            WaitingForStart => {}
+                            InsideMessage => {
+                                return Ok(&message_buffer[..current_bytes]);
+                            }
+                            InsideMessageEscaping => {
+                                return Err(DecoderError::InvalidEscaped(next));
+                            }
+                        }
+                    }
+                    ESCAPE_BYTE => {
+                        match state {
+                            WaitingForStart => {}
+                            InsideMessage => {
+                                state = InsideMessageEscaping;
+                            }
+                            InsideMessageEscaping => {
+                                return Err(DecoderError::InvalidEscaped(next));
+                            }
+                        }
+                    }
+                    _ => {
+                        let byte = match state {
+                            WaitingForStart => {continue}
+                            InsideMessage => next,
+                            InsideMessageEscaping => {
+                                match next ^ 0xFF {
+                                    byte @ START_BYTE |
+                                    byte @ END_BYTE |
+                                    byte @ ESCAPE_BYTE => {
+                                        byte
+                                    }
+                                    _ => {
+                                        // We got an escaped character that didn't need escaping
+                                        // We're probably getting invalid data.
+                                        return Err(DecoderError::InvalidEscaped(next));
+                                    },
+                                }
+                            }
+                        };
+                        if let Some(slot) = message_buffer.get_mut(current_bytes) {
+                            *slot = byte;
+                            current_bytes += 1;
+                            state = InsideMessage;
+                        } else {
+                            // Writing outside memory.
+                            // We're getting longer messages than we should.
+                            return Err(DecoderError::BufferTooSmall);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[derive(Debug)]
+pub enum DecoderError {
+    IoError(std::io::Error),
+    ReaderEndOfFile,
+    BufferTooSmall,
+    InvalidEscaped(u8),
+}
+
+#[cfg(test)]
+#[derive(Debug, Clone)]
+enum ReaderState {
+    WaitingForStart,
+    InsideMessage,
+    InsideMessageEscaping,
+}
 
 #[cfg(test)]
 mod tests {
-    use crate::byte_stuffing::{DecoderError, encode_iter, from_reader};
+    use crate::byte_stuffing::{encode_iter, from_reader};
 
     #[test]
     fn encode_test() {
@@ -181,7 +172,7 @@ mod tests {
             }
             Err(error) => {
                 println!("message_buffer: {:?}", message_buffer);
-                Err::<&[u8], _>(error).unwrap();
+                panic!("{:?}", error);
             }
         }
     }
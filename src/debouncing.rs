@@ -22,11 +22,6 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-#![no_std]
-// use no_std_compat::prelude::v1::*;
-
-use core::mem::MaybeUninit;
-
 pub struct Debouncer<'a> {
     patterns: &'a mut [u8],
 }
@@ -40,7 +35,7 @@ pub enum DebounceResult {
 }
 
 impl<'a> Debouncer<'a> {
-    pub fn new(slice: &'a mut [u8]) -> Debouncer {
+    pub fn new(slice: &'a mut [u8]) -> Debouncer<'a> {
         Debouncer{
             patterns: slice
         }
@@ -63,16 +58,17 @@ impl<'a> Debouncer<'a> {
             self.patterns[key_no] = 0b0000000;
         }
 
-        return result;
+        result
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{DebounceResult, Debouncer};
+    use crate::debouncing::{DebounceResult, Debouncer};
     #[test]
     fn it_works() {
-        let mut db = Debouncer::new(1);
+        let mut patterns = [0x00; 1];
+        let mut db = Debouncer::new(&mut patterns);
         //activate
         assert!(db.update(0, true) == DebounceResult::NoChange);
         assert!(db.update(0, true) == DebounceResult::NoChange);
@@ -1,15 +1,15 @@
 use ufmt::{Formatter, uDisplay, uWrite};
 use crate::{bmi160_error, ssd1306_error};
 
-pub enum UDisplayError<I2CError> {
-    BMI160Error(bmi160_error::Error<I2CError>),
-    SSD1306Error(ssd1306_error::Error<I2CError>),
+pub enum UDisplayError<E> {
+    BMI160Error(bmi160_error::Error<E>),
+    SSD1306Error(ssd1306_error::Error<E>),
     PostcardError(postcard::Error),
 }
 
 #[cfg(feature = "string-errors")]
-impl<I2CError> uDisplay for UDisplayError<I2CError>
-    where I2CError: embedded_hal::i2c::Error {
+impl<E> uDisplay for UDisplayError<E>
+    where E: embedded_hal::i2c::Error {
     fn fmt<W>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error> where W: uWrite + ?Sized {
         match self {
             UDisplayError::BMI160Error(error) => {
@@ -17,7 +17,7 @@ impl<I2CError> uDisplay for UDisplayError<I2CError>
                 error.fmt(f)
             }
             UDisplayError::SSD1306Error(error) => {
-                f.write_str("BMI160 error: ")?;
+                f.write_str("SSD1306 error: ")?;
                 error.fmt(f)
             }
             UDisplayError::PostcardError(error) => {
@@ -27,17 +27,17 @@ impl<I2CError> uDisplay for UDisplayError<I2CError>
     }
 }
 
-impl<I2CError> From<bmi160_error::Error<I2CError>> for UDisplayError<I2CError> {
-    fn from(value: bmi160_error::Error<I2CError>) -> Self {
+impl<E> From<bmi160_error::Error<E>> for UDisplayError<E> {
+    fn from(value: bmi160_error::Error<E>) -> Self {
         Self::BMI160Error(value)
     }
 }
-impl<I2CError> From<ssd1306_error::Error<I2CError>> for UDisplayError<I2CError> {
-    fn from(value: ssd1306_error::Error<I2CError>) -> Self {
+impl<E> From<ssd1306_error::Error<E>> for UDisplayError<E> {
+    fn from(value: ssd1306_error::Error<E>) -> Self {
         Self::SSD1306Error(value)
     }
 }
-impl<I2CError> From<postcard::Error> for UDisplayError<I2CError> {
+impl<E> From<postcard::Error> for UDisplayError<E> {
     fn from(value: postcard::Error) -> Self {
         Self::PostcardError(value)
     }
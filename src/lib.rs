@@ -1,10 +1,22 @@
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 pub mod bmi160;
+pub mod bmi160_config;
+pub mod bmi160_fifo;
+pub mod bmi160_interrupt;
 pub mod bmi160_error;
 pub mod bmi160_registers;
 pub mod byte_stuffing;
+pub mod debouncing;
 pub mod error;
 pub mod ssd1306;
+pub mod ssd1306_bitmap;
+pub mod ssd1306_config;
 pub mod ssd1306_error;
 pub mod ssd1306_font;
+#[cfg(feature = "graphics")]
+pub mod ssd1306_graphics;
+pub mod ssd1306_interface;
 pub mod ssd1306_registers;
+pub mod ssd1306_size;
+#[cfg(test)]
+mod test_support;
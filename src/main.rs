@@ -3,7 +3,12 @@
 #![no_main]
 
 mod ssd1306;
+mod ssd1306_bitmap;
+mod ssd1306_config;
 mod bmi160;
+mod bmi160_config;
+mod bmi160_fifo;
+mod bmi160_interrupt;
 mod bmi160_registers;
 mod bmi160_error;
 mod byte_stuffing;
@@ -13,6 +18,8 @@ mod print;
 mod ssd1306_registers;
 mod ssd1306_error;
 mod ssd1306_font;
+mod ssd1306_interface;
+mod ssd1306_size;
 mod debouncing;
 
 use core::cell::RefCell;
@@ -22,8 +29,10 @@ use arduino_hal::Peripherals;
 use arduino_hal::prelude::*;
 use crate::debouncing::Debouncer;
 use crate::debouncing::DebounceResult::Pressed;
-use crate::ssd1306::BUFFER_SIZE;
+use crate::ssd1306_config::Config;
+use crate::ssd1306_interface::I2cInterface;
 use crate::ssd1306_registers::WHITE;
+use crate::ssd1306_size::{DisplaySize, DisplaySize128x64};
 
 enum GameState {
     Menu,
@@ -52,8 +61,10 @@ fn main() -> ! {
         let mut button1 = pins.d5.into_pull_up_input();
         let mut button2 = pins.d4.into_pull_up_input();
         let i2c_ref_cell = RefCell::new(i2c);
-        let mut buffer = [0x00; ssd1306::BUFFER_SIZE];
-        let display_result = ssd1306::DisplayDriver::new(embedded_hal_bus::i2c::RefCellDevice::new(&i2c_ref_cell), None, &mut buffer);
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        // One checksum per 8 columns of a page, the full 1 KB shadow wouldn't fit next to the buffer
+        let mut shadow = [0x00; 128];
+        let display_result = ssd1306::DisplayDriver::new(I2cInterface::new(embedded_hal_bus::i2c::RefCellDevice::new(&i2c_ref_cell), None), &mut buffer, DisplaySize128x64, Config::default());
         let mut display = match display_result {
             Ok(display) => {
                 display
@@ -62,6 +73,7 @@ fn main() -> ! {
                 return Err(error.into());
             }
         };
+        display.set_shadow(Some(&mut shadow))?;
         let mut debouncer_storage = [0x00; 2];
         let mut debouncer = Debouncer::new(&mut debouncer_storage[..]);
         println!("Started");
@@ -91,12 +103,12 @@ fn main() -> ! {
             display.set_cursor(0, 0);
             // display.draw_string("Sequence memory! Try buttons.");
             display.fill_screen(WHITE);
-            display.display()?;
+            display.flush()?;
             arduino_hal::delay_ms(100);
             display.clear_display();
             display.draw_char('a')?;
             // display.draw_string("Sequence memory! Try buttons.");
-            display.display()?;
+            display.flush()?;
 
             println!("Loop");
             led.set_high();
@@ -1,101 +1,462 @@
 #![allow(unused_variables, dead_code)]
 
 use core::mem::swap;
-// use codepage_437::CP437_CONTROL;
-use embedded_hal::i2c::{I2c, Operation};
+use embedded_hal::digital::OutputPin;
+use ufmt::uWrite;
+use crate::ssd1306_bitmap::{Bitmap, BitmapFormat};
+use crate::ssd1306_config::{Config, Rotation};
 use crate::ssd1306_error::Error;
-use crate::ssd1306_font::{FONT, FONT_HEIGHT, FONT_HEIGHT_1, FONT_WIDTH, FONT_WIDTH_1};
+use crate::ssd1306_font::{cp437, Font, FONT_5X7};
+use crate::ssd1306_interface::DisplayInterface;
 use crate::ssd1306_registers::*;
+use crate::ssd1306_size::{DisplaySize, DisplaySize128x64};
 
-const DEFAULT_ADDRESS: u8 = 0x3C;
-const TEMP_REGISTER: u8 = 0x15;
-pub const BUFFER_SIZE: usize = LCDWIDTH as usize * ((LCDHEIGHT as usize + 7) / 8);
+/// Number of frames between each scroll step. The discriminants are the values the
+/// SSD1306 expects in the scroll setup commands.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScrollSpeed {
+    Frames2 = 0b111,
+    Frames3 = 0b100,
+    Frames4 = 0b101,
+    Frames5 = 0b000,
+    Frames25 = 0b110,
+    Frames64 = 0b001,
+    Frames128 = 0b010,
+    Frames256 = 0b011,
+}
 
-pub struct DisplayDriver<'buffer, I2C> {
-    i2c: I2C,
-    address: u8,
-    buffer: &'buffer mut [u8; BUFFER_SIZE],
-    cursor_x: u16,
-    cursor_y: u16,
+/// A rectangle in display coordinates, `w` pixels wide and `h` pixels high.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub x: i16,
+    pub y: i16,
+    pub w: i16,
+    pub h: i16,
 }
 
-impl<'buffer, I2C> DisplayDriver<'buffer, I2C> {}
-
-impl<'buffer, I2C: I2c> DisplayDriver<'buffer, I2C> {
-    pub fn new(mut i2c: I2C, address: Option<u8>, buffer: &'buffer mut [u8; BUFFER_SIZE]) -> Result<Self, Error<I2C::Error>> {
-        let address = address.unwrap_or(DEFAULT_ADDRESS);
-        // i2c.write(address, &[0xE3])?;
-        let vcc_state = SWITCHCAPVCC;
-        i2c.write(address, &[0x00, DISPLAYOFF, SETDISPLAYCLOCKDIV, 0x80, SETMULTIPLEX, (LCDHEIGHT - 1).try_into().unwrap()])?;
-        i2c.write(address, &[0x00, SETDISPLAYOFFSET, 0x00, SETSTARTLINE | 0x00, CHARGEPUMP])?;
-        if vcc_state == EXTERNALVCC {
-            i2c.write(address, &[0x00, 0x10])?;
-        } else {
-            i2c.write(address, &[0x00, 0x14])?;
+impl Rect {
+    pub const fn new(x: i16, y: i16, w: i16, h: i16) -> Self {
+        Self { x, y, w, h }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.w <= 0 || self.h <= 0
+    }
+
+    pub fn contains(&self, x: i16, y: i16) -> bool {
+        x >= self.x && y >= self.y
+            && (x as i32) < self.x as i32 + self.w as i32
+            && (y as i32) < self.y as i32 + self.h as i32
+    }
+
+    /// The part of this rectangle that is also inside `other`. Empty if they don't overlap.
+    pub fn intersection(&self, other: &Rect) -> Rect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x as i32 + self.w as i32).min(other.x as i32 + other.w as i32);
+        let bottom = (self.y as i32 + self.h as i32).min(other.y as i32 + other.h as i32);
+        if right <= x as i32 || bottom <= y as i32 {
+            return Rect::new(x, y, 0, 0);
         }
-        i2c.write(address, &[0x00, MEMORYMODE, 0x01, 0xA1, 0xC8])?;
+        Rect::new(x, y, (right - x as i32) as i16, (bottom - y as i32) as i16)
+    }
+}
 
-        let com_pins = 0x02;
-        let contrast = 0x8F;
-        i2c.write(address, &[0x00, SETCOMPINS, com_pins])?;
-        i2c.write(address, &[0x00, SETCONTRAST, contrast])?;
-        if vcc_state == EXTERNALVCC {
-            i2c.write(address, &[0x00, SETPRECHARGE, 0x22])?;
-        } else {
-            i2c.write(address, &[0x00, SETPRECHARGE, 0xF1])?;
+/// Horizontal placement of each line in `draw_text_box`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// What `draw_text_box` does when the text doesn't fit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Overflow {
+    /// Stops after the last line that fits
+    Truncate,
+    /// Like `Truncate`, but ends the last line with "..."
+    Ellipsis,
+}
+
+/// Bounding box of the buffer bytes changed since the last flush, in columns and pages (inclusive).
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct DirtyArea {
+    first_column: u8,
+    last_column: u8,
+    first_page: u8,
+    last_page: u8,
+}
+
+impl DirtyArea {
+    fn all<SIZE: DisplaySize>() -> Self {
+        Self {
+            first_column: 0,
+            last_column: SIZE::WIDTH - 1,
+            first_page: 0,
+            last_page: SIZE::PAGES - 1,
+        }
+    }
+
+    fn including(self, column: u8, page: u8) -> Self {
+        Self {
+            first_column: self.first_column.min(column),
+            last_column: self.last_column.max(column),
+            first_page: self.first_page.min(page),
+            last_page: self.last_page.max(page),
+        }
+    }
+}
+
+/// Checksums of what the display RAM holds, one per page for every `segment_width` columns.
+/// With one column per segment every byte has its own checksum, so the comparison is exact.
+struct Shadow<'buffer> {
+    checksums: &'buffer mut [u8],
+    segment_width: u8,
+    segments_per_page: u8,
+    /// False until everything has been sent once, and whenever the display RAM may have changed
+    /// without the checksums being updated
+    valid: bool,
+}
+
+impl<'buffer> Shadow<'buffer> {
+    fn new<SIZE: DisplaySize>(checksums: &'buffer mut [u8]) -> Option<Self> {
+        let segments = (checksums.len() / SIZE::PAGES as usize).min(SIZE::WIDTH as usize);
+        if segments == 0 {
+            return None;
+        }
+        let segment_width = (SIZE::WIDTH as usize).div_ceil(segments);
+        Some(Self {
+            checksums,
+            segment_width: segment_width as u8,
+            segments_per_page: (SIZE::WIDTH as usize).div_ceil(segment_width) as u8,
+            valid: false,
+        })
+    }
+}
+
+/// A contrast change in progress, see `DisplayDriver::fade_to`.
+#[derive(Clone, Copy, Debug)]
+struct Fade {
+    start: u8,
+    target: u8,
+    duration_ms: u16,
+    elapsed_ms: u16,
+}
+
+impl Fade {
+    fn contrast(&self) -> u8 {
+        if self.elapsed_ms >= self.duration_ms {
+            return self.target;
+        }
+        let change = (self.target as i32 - self.start as i32) * self.elapsed_ms as i32 / self.duration_ms as i32;
+        (self.start as i32 + change) as u8
+    }
+}
+
+/// CRC-8 with polynomial 0x07. Unlike a plain xor it depends on where each bit is, so it always
+/// changes when one or two bits within 127 bits of each other change, such as the neighbouring
+/// pixels of a diagonal line.
+fn checksum(bytes: impl Iterator<Item=u8>) -> u8 {
+    bytes.fold(0, |sum, byte| {
+        let mut crc = sum ^ byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 };
+        }
+        crc
+    })
+}
+
+pub struct DisplayDriver<'buffer, DI, SIZE: DisplaySize = DisplaySize128x64> {
+    interface: DI,
+    size: SIZE,
+    config: Config,
+    buffer: &'buffer mut SIZE::Buffer,
+    cursor_x: i16,
+    cursor_y: i16,
+    font: &'static Font,
+    text_scale: u8,
+    replacement_char: Option<u8>,
+    text_scroll: bool,
+    clip: Rect,
+    strict: bool,
+    dirty: Option<DirtyArea>,
+    shadow: Option<Shadow<'buffer>>,
+    fade: Option<Fade>,
+}
+
+impl<'buffer, DI, SIZE: DisplaySize> DisplayDriver<'buffer, DI, SIZE> {
+    /// Whether the pixel is set in the buffer. Pixels outside the screen are never set.
+    pub fn get_pixel(&self, x: i16, y: i16) -> bool {
+        if x < 0 || y < 0 || x >= self.width() || y >= self.height() {
+            return false;
+        }
+        let (x, y) = self.to_physical(x, y);
+        let byte_index = SIZE::PAGES as usize * x as usize + (y as usize / 8);
+        self.buffer.as_ref()[byte_index] & (1 << (y % 8)) != 0
+    }
+    /// Width of the canvas, which is the panel's height when rotated by 90 or 270 degrees.
+    pub fn width(&self) -> i16 {
+        Self::canvas_size(self.config.rotation).0
+    }
+    pub fn height(&self) -> i16 {
+        Self::canvas_size(self.config.rotation).1
+    }
+    fn canvas_size(rotation: Rotation) -> (i16, i16) {
+        match rotation {
+            Rotation::Rotate0 | Rotation::Rotate180 => (SIZE::WIDTH as i16, SIZE::HEIGHT as i16),
+            Rotation::Rotate90 | Rotation::Rotate270 => (SIZE::HEIGHT as i16, SIZE::WIDTH as i16),
+        }
+    }
+    /// Maps canvas coordinates to the panel's columns and rows.
+    fn to_physical(&self, x: i16, y: i16) -> (i16, i16) {
+        let area = self.area_to_physical(Rect::new(x, y, 1, 1));
+        (area.x, area.y)
+    }
+    fn area_to_physical(&self, area: Rect) -> Rect {
+        let (width, height) = (SIZE::WIDTH as i16, SIZE::HEIGHT as i16);
+        match self.config.rotation {
+            Rotation::Rotate0 => area,
+            Rotation::Rotate90 => Rect::new(width - area.y - area.h, area.x, area.h, area.w),
+            Rotation::Rotate180 => Rect::new(width - area.x - area.w, height - area.y - area.h, area.w, area.h),
+            Rotation::Rotate270 => Rect::new(area.y, height - area.x - area.w, area.h, area.w),
         }
-        i2c.write(address, &[0x00, SETVCOMDETECT, 0x40, DISPLAYALLON_RESUME, NORMALDISPLAY, DEACTIVATE_SCROLL, DISPLAYON])?;
+    }
+    /// The framebuffer, column after column with `SIZE::PAGES` bytes per column.
+    pub fn get_buffer(&self) -> &[u8] {
+        self.buffer.as_ref()
+    }
+    /// Marks the whole buffer as dirty, since changes made through it can't be tracked.
+    pub fn get_buffer_mut(&mut self) -> &mut [u8] {
+        self.dirty = Some(DirtyArea::all::<SIZE>());
+        self.buffer.as_mut()
+    }
+    /// Gives back the interface and the buffer, for example to use the I2C bus for something
+    /// else with `I2cInterface::release`.
+    pub fn release(self) -> (DI, &'buffer mut SIZE::Buffer) {
+        (self.interface, self.buffer)
+    }
+}
+
+impl<'buffer, DI: DisplayInterface, SIZE: DisplaySize> DisplayDriver<'buffer, DI, SIZE> {
+    /// Initializes a display of the given size. The buffer must be `SIZE::BUFFER_SIZE` bytes,
+    /// for example `[0x00; DisplaySize128x32::BUFFER_SIZE]`. The interface is an `I2cInterface`
+    /// or an `SpiInterface`.
+    pub fn new(mut interface: DI, buffer: &'buffer mut SIZE::Buffer, size: SIZE, config: Config) -> Result<Self, Error<DI::Error>> {
+        let vcc_state = config.vcc_state;
+        interface.write_command(&[DISPLAYOFF, SETDISPLAYCLOCKDIV, config.clock_div, SETMULTIPLEX, SIZE::HEIGHT - 1])?;
+        interface.write_command(&[SETDISPLAYOFFSET, config.display_offset & 0x3F, SETSTARTLINE | (config.start_line & 0x3F), CHARGEPUMP])?;
+        interface.write_command(&[charge_pump(vcc_state)])?;
+        // Column 127 is mapped to SEG0 and the COM pins are scanned backwards when not flipped
+        let segment_remap = if config.flip_horizontal { SEGREMAP } else { SEGREMAP | 0x01 };
+        let com_scan = if config.flip_vertical { COMSCANINC } else { COMSCANDEC };
+        interface.write_command(&[MEMORYMODE, 0x01, segment_remap, com_scan])?;
+
+        let com_pins = SIZE::COM_PINS;
+        let contrast = config.contrast;
+        interface.write_command(&[SETCOMPINS, com_pins])?;
+        interface.write_command(&[SETCONTRAST, contrast])?;
+        let precharge = match config.precharge {
+            Some(precharge) => precharge,
+            None if vcc_state == EXTERNALVCC => 0x22,
+            None => 0xF1,
+        };
+        interface.write_command(&[SETPRECHARGE, precharge])?;
+        interface.write_command(&[SETVCOMDETECT, config.vcomh, DISPLAYALLON_RESUME, NORMALDISPLAY, DEACTIVATE_SCROLL, DISPLAYON])?;
 
-        Ok(Self {
-            i2c,
-            address,
+        let mut display = Self {
+            interface,
+            size,
+            config,
             buffer,
             cursor_x: 0,
             cursor_y: 0,
-        })
+            font: &FONT_5X7,
+            text_scale: 1,
+            replacement_char: Some(b'?'),
+            text_scroll: false,
+            clip: Rect::new(0, 0, 0, 0),
+            strict: false,
+            // Nothing is known about what the panel shows yet
+            dirty: Some(DirtyArea::all::<SIZE>()),
+            shadow: None,
+            fade: None,
+        };
+        display.set_clip_rect(None);
+        Ok(display)
+    }
+
+    /// Pulses the RES pin before initializing, for modules that don't reset themselves on power up.
+    /// `delay_fn` waits the given number of milliseconds.
+    pub fn new_with_reset<RST, F>(interface: DI, reset: &mut RST, delay_fn: F, buffer: &'buffer mut SIZE::Buffer, size: SIZE, config: Config) -> Result<Self, Error<DI::Error>>
+    where RST: OutputPin, F: Fn(u16) {
+        // The datasheet asks for at least 3us low, the supply needs a moment to settle before that
+        reset.set_high().map_err(|_| Error::PinError)?;
+        delay_fn(1);
+        reset.set_low().map_err(|_| Error::PinError)?;
+        delay_fn(10);
+        reset.set_high().map_err(|_| Error::PinError)?;
+        Self::new(interface, buffer, size, config)
+    }
+
+    pub fn start_of_data(&mut self) -> Result<(), Error<DI::Error>> {
+        let last_x_pixel_index: u8 = SIZE::WIDTH - 1;
+        let last_y_byte_index: u8 = SIZE::PAGES - 1;
+        self.set_address_window(0x00, last_x_pixel_index, 0x00, last_y_byte_index)
+    }
+
+    /// Limits the following data writes to the given columns and pages, inclusive.
+    fn set_address_window(&mut self, first_column: u8, last_column: u8, first_page: u8, last_page: u8) -> Result<(), Error<DI::Error>> {
+        self.interface.write_command(&[COLUMNADDR, first_column, last_column, PAGEADDR, first_page, last_page])?;
+        Ok(())
     }
 
-    pub fn start_of_data(&mut self) -> Result<(), Error<I2C::Error>> {
-        // self.i2c.write(self.address, &[PAGEADDR, 0, (LCDHEIGHT/2 - 1).try_into().unwrap(), COLUMNADDR, 0, (LCDWIDTH - 1).try_into().unwrap()])?;
-        let last_x_pixel_index: u8 = (LCDWIDTH - 1) as u8;
-        let last_y_byte_index: u8 = ((LCDHEIGHT / 8) - 1) as u8;
-        self.i2c.write(self.address, &[0x00, COLUMNADDR, 0x00, last_x_pixel_index, PAGEADDR, 0x00, last_y_byte_index])?;
+    /// Sends the whole buffer, or with a shadow buffer only the parts that differ from what was
+    /// sent before.
+    pub fn display(&mut self) -> Result<(), Error<DI::Error>> {
+        if self.shadow.is_some() {
+            self.send_changes(DirtyArea::all::<SIZE>())?;
+        } else {
+            self.display_num(SIZE::BUFFER_SIZE)?;
+        }
+        self.dirty = None;
+        Ok(())
+    }
+
+    /// Sends only the columns and pages that have been drawn to since the last `flush` or
+    /// `display`. Does nothing if the buffer is unchanged.
+    pub fn flush(&mut self) -> Result<(), Error<DI::Error>> {
+        let Some(area) = self.dirty else {
+            return Ok(());
+        };
+        if self.shadow.is_some() {
+            self.send_changes(area)?;
+        } else {
+            self.send_window(area.first_column, area.last_column, area.first_page, area.last_page)?;
+        }
+        self.dirty = None;
+        Ok(())
+    }
+
+    /// Sends the buffer bytes of the given columns and pages, inclusive.
+    fn send_window(&mut self, first_column: u8, last_column: u8, first_page: u8, last_page: u8) -> Result<(), Error<DI::Error>> {
+        self.set_address_window(first_column, last_column, first_page, last_page)?;
+
+        // The display is in vertical addressing mode, so the window is filled column by column
+        let mut chunk = [0x00; 32];
+        let mut length = 0;
+        for column in first_column as usize..=last_column as usize {
+            for page in first_page as usize..=last_page as usize {
+                chunk[length] = self.buffer.as_ref()[column * SIZE::PAGES as usize + page];
+                length += 1;
+                if length == chunk.len() {
+                    self.send_data(&chunk)?;
+                    length = 0;
+                }
+            }
+        }
+        if length > 0 {
+            self.send_data(&chunk[..length])?;
+        }
+        Ok(())
+    }
+
+    /// Keeps checksums of what was sent in `shadow`, so that `display` and `flush` only send the
+    /// runs of columns in each page that changed since. A shadow of `SIZE::BUFFER_SIZE` bytes
+    /// compares every byte. A smaller one checks several columns per byte, down to `SIZE::PAGES`
+    /// bytes for a checksum per page, which saves RAM but sends more. Several changes under the
+    /// same checksum can then cancel out and be missed until `resend_all` is called.
+    pub fn set_shadow(&mut self, shadow: Option<&'buffer mut [u8]>) -> Result<(), Error<DI::Error>> {
+        self.shadow = match shadow {
+            Some(checksums) => Some(Shadow::new::<SIZE>(checksums).ok_or(Error::ShadowTooSmall)?),
+            None => None,
+        };
         Ok(())
     }
 
-    pub fn display(&mut self) -> Result<(), Error<I2C::Error>> {
-        // self.start_of_data()?;
-        // self.i2c.write(self.address, &[0x00, PAGEADDR, 0, 0xFF, COLUMNADDR, 0])?;
-        // self.i2c.write(self.address, &[0x00, (LCDWIDTH - 1).try_into().unwrap()])?;
+    /// Compares the pages and columns in `area` with the shadow and sends the changed runs.
+    /// Checks the whole buffer while the shadow isn't valid.
+    fn send_changes(&mut self, area: DirtyArea) -> Result<(), Error<DI::Error>> {
+        let Some(mut shadow) = self.shadow.take() else {
+            return Ok(());
+        };
+        let area = if shadow.valid { area } else { DirtyArea::all::<SIZE>() };
+        let result = self.send_changed_segments(&mut shadow, area);
+        // After a failed write it's unknown what made it to the display
+        shadow.valid = result.is_ok();
+        self.shadow = Some(shadow);
+        result
+    }
 
-        // self.i2c.write(self.address, &subset)?;
-        self.display_num(BUFFER_SIZE)?;
+    fn send_changed_segments(&mut self, shadow: &mut Shadow, area: DirtyArea) -> Result<(), Error<DI::Error>> {
+        let width = shadow.segment_width as usize;
+        let first_segment = area.first_column as usize / width;
+        let last_segment = area.last_column as usize / width;
+        for page in area.first_page..=area.last_page {
+            let mut run_start = None;
+            // One past the last segment, to send a run that reaches it
+            for segment in first_segment..=last_segment + 1 {
+                let end_column = ((segment + 1) * width).min(SIZE::WIDTH as usize);
+                let changed = segment <= last_segment && {
+                    let bytes = (segment * width..end_column)
+                        .map(|column| self.buffer.as_ref()[column * SIZE::PAGES as usize + page as usize]);
+                    let sum = checksum(bytes);
+                    let index = page as usize * shadow.segments_per_page as usize + segment;
+                    let changed = !shadow.valid || shadow.checksums[index] != sum;
+                    shadow.checksums[index] = sum;
+                    changed
+                };
+                match (run_start, changed) {
+                    (None, true) => run_start = Some(segment),
+                    (Some(start), false) => {
+                        // The last segment is narrower when the width doesn't divide evenly
+                        let last_column = (segment * width).min(SIZE::WIDTH as usize) - 1;
+                        self.send_window((start * width) as u8, last_column as u8, page, page)?;
+                        run_start = None;
+                    }
+                    _ => {}
+                }
+            }
+        }
         Ok(())
     }
 
-    pub fn display_num(&mut self, num: usize) -> Result<(), Error<I2C::Error>> {
+    /// Marks the whole buffer as changed, so that the next `flush` sends all of it.
+    pub fn mark_all_dirty(&mut self) {
+        self.dirty = Some(DirtyArea::all::<SIZE>());
+    }
+
+    /// Like `mark_all_dirty`, but also makes a shadow buffer send everything instead of only
+    /// what it sees changed. Needed after the display RAM was changed some other way, for example
+    /// with `ssd1306_command`.
+    pub fn resend_all(&mut self) {
+        self.mark_all_dirty();
+        if let Some(shadow) = &mut self.shadow {
+            shadow.valid = false;
+        }
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty.is_some()
+    }
+
+    fn send_data(&mut self, data: &[u8]) -> Result<(), Error<DI::Error>> {
+        self.interface.write_data(data)
+    }
+
+    pub fn display_num(&mut self, num: usize) -> Result<(), Error<DI::Error>> {
+        // Bypasses the shadow buffer, which then no longer matches the display
+        self.resend_all();
         self.start_of_data()?;
-        // self.i2c.write(self.address, &[0x00, PAGEADDR, 0, (LCDHEIGHT - 1).try_into().unwrap(), COLUMNADDR, 0, (LCDWIDTH - 1).try_into().unwrap()])?;
-        // self.i2c.write(self.address, &[0x00, (LCDWIDTH - 1).try_into().unwrap()])?;
-
-        // self.i2c.write(self.address, &subset)?;
-        // self.i2c.transaction(self.address, &mut [
-        //     Operation::Write(&mut [0x40]),
-        //     Operation::Write(&self.buffer[..num]),
-        // ])?;
         let chunk_size = 32;
         for i in 0..num.div_ceil(chunk_size) {
-            // for i in 0..1 {
             let first = chunk_size * i;
             if first < num {
                 let mut last = chunk_size * i + chunk_size - 1;
                 if last > num - 1 {
                     last = num - 1
                 }
-                self.i2c.transaction(self.address, &mut [
-                    Operation::Write(&mut [0x40]),
-                    Operation::Write(&self.buffer[first..=last]),
-                ])?;
+                self.interface.write_data(&self.buffer.as_ref()[first..=last])?;
             }
         }
         Ok(())
@@ -105,62 +466,224 @@ impl<'buffer, I2C: I2c> DisplayDriver<'buffer, I2C> {
         self.fill_screen(BLACK);
     }
 
-    pub fn invert_display(&mut self, inverted: bool) -> Result<(), Error<I2C::Error>> {
+    pub fn invert_display(&mut self, inverted: bool) -> Result<(), Error<DI::Error>> {
         if inverted {
-            self.i2c.write(self.address, &[0x00, INVERTDISPLAY])?;
+            self.interface.write_command(&[INVERTDISPLAY])?;
         } else {
-            self.i2c.write(self.address, &[0x00, NORMALDISPLAY])?;
+            self.interface.write_command(&[NORMALDISPLAY])?;
         }
         Ok(())
     }
 
-    pub fn dim(&mut self, dim: bool) -> Result<(), Error<I2C::Error>> {
+    /// Turns the contrast all the way down, or back to the configured contrast.
+    pub fn dim(&mut self, dim: bool) -> Result<(), Error<DI::Error>> {
         if dim {
-            self.i2c.write(self.address, &[0x00, SETCONTRAST, 0x0])?;
+            self.interface.write_command(&[SETCONTRAST, 0x0])?;
         } else {
-            self.i2c.write(self.address, &[0x00, SETCONTRAST, 0x8F])?;
+            self.interface.write_command(&[SETCONTRAST, self.config.contrast])?;
         }
         Ok(())
     }
 
-    pub fn draw_pixel(&mut self, x: u16, y: u16, color: u8) -> Result<(), Error<I2C::Error>> {
-        if x >= LCDWIDTH || y >= LCDHEIGHT {
-            return Err(Error::OutsideScreenAccess { x: x as i16, y: y as i16 });
+    /// Sets the brightness, 0x00 to 0xFF. `dim(false)` returns to this contrast.
+    pub fn set_contrast(&mut self, contrast: u8) -> Result<(), Error<DI::Error>> {
+        self.interface.write_command(&[SETCONTRAST, contrast])?;
+        self.config.contrast = contrast;
+        Ok(())
+    }
+
+    /// See `Config::precharge`.
+    pub fn set_precharge(&mut self, precharge: u8) -> Result<(), Error<DI::Error>> {
+        self.interface.write_command(&[SETPRECHARGE, precharge])?;
+        self.config.precharge = Some(precharge);
+        Ok(())
+    }
+
+    /// See `Config::vcomh`.
+    pub fn set_vcomh(&mut self, vcomh: u8) -> Result<(), Error<DI::Error>> {
+        self.interface.write_command(&[SETVCOMDETECT, vcomh])?;
+        self.config.vcomh = vcomh;
+        Ok(())
+    }
+
+    /// Starts changing the contrast to `target` over `duration_ms` milliseconds. Nothing is sent
+    /// until `tick` is called.
+    pub fn fade_to(&mut self, target: u8, duration_ms: u16) {
+        self.fade = Some(Fade {
+            start: self.config.contrast,
+            target,
+            duration_ms,
+            elapsed_ms: 0,
+        });
+    }
+
+    /// Advances a fade started by `fade_to` by `elapsed_ms` milliseconds, sending the contrast if
+    /// it changed. Returns whether the fade is still running.
+    pub fn tick(&mut self, elapsed_ms: u16) -> Result<bool, Error<DI::Error>> {
+        let Some(mut fade) = self.fade else {
+            return Ok(false);
+        };
+        fade.elapsed_ms = fade.elapsed_ms.saturating_add(elapsed_ms).min(fade.duration_ms);
+        let contrast = fade.contrast();
+        if contrast != self.config.contrast {
+            self.set_contrast(contrast)?;
+        }
+        let running = fade.elapsed_ms < fade.duration_ms;
+        self.fade = if running { Some(fade) } else { None };
+        Ok(running)
+    }
+
+    pub fn is_fading(&self) -> bool {
+        self.fade.is_some()
+    }
+
+    /// Turns the panel and the charge pump off. The display RAM keeps its contents, so `wake`
+    /// shows the same image again without resending the buffer.
+    pub fn sleep(&mut self) -> Result<(), Error<DI::Error>> {
+        self.interface.write_command(&[DISPLAYOFF, CHARGEPUMP, 0x10])
+    }
+
+    pub fn wake(&mut self) -> Result<(), Error<DI::Error>> {
+        self.interface.write_command(&[CHARGEPUMP, charge_pump(self.config.vcc_state), DISPLAYON])
+    }
+
+    /// Confines all drawing to `clip`, or to the whole screen if `None`.
+    pub fn set_clip_rect(&mut self, clip: Option<Rect>) {
+        self.clip = match clip {
+            Some(clip) => clip.intersection(&self.screen()),
+            None => self.screen(),
+        };
+    }
+    fn screen(&self) -> Rect {
+        Rect::new(0, 0, self.width(), self.height())
+    }
+    /// Changes the drawing coordinates for everything drawn from now on, the buffer is left as
+    /// it is. Resets the clip rectangle, since the screen's size may change.
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        self.config.rotation = rotation;
+        self.set_clip_rect(None);
+    }
+    pub fn clip_rect(&self) -> Rect {
+        self.clip
+    }
+    /// In strict mode, drawing outside the screen or clip rectangle fails with
+    /// `Error::OutsideScreenAccess` instead of being silently clipped. Useful for debugging layouts.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+    pub fn draw_pixel(&mut self, x: i16, y: i16, color: u8) -> Result<(), Error<DI::Error>> {
+        if !self.clip.contains(x, y) {
+            return self.outside(x, y);
         }
-        let byte = 1 << (y % 8);
-        let byte_index = (LCDHEIGHT / 8) * x + (y / 8);
-        if let Some(view) = self.buffer.get_mut(byte_index as usize) {
-            *view = *view | byte;
+        let (column, row) = self.to_physical(x, y);
+        let byte = 1 << (row % 8);
+        let byte_index = SIZE::PAGES as usize * column as usize + (row as usize / 8);
+        if byte_index < SIZE::BUFFER_SIZE {
+            self.write_byte(byte_index, byte, color);
             Ok(())
         } else {
             Err(Error::OutsideScreenAccess {
-                x: x as i16,
-                y: y as i16,
+                x,
+                y,
             })
         }
     }
+    /// Applies `color` to the masked bits of a buffer byte and records the byte as dirty.
+    fn write_byte(&mut self, byte_index: usize, mask: u8, color: u8) {
+        apply_color(&mut self.buffer.as_mut()[byte_index], mask, color);
+        let column = (byte_index / SIZE::PAGES as usize) as u8;
+        let page = (byte_index % SIZE::PAGES as usize) as u8;
+        self.dirty = Some(match self.dirty {
+            Some(area) => area.including(column, page),
+            None => DirtyArea { first_column: column, last_column: column, first_page: page, last_page: page },
+        });
+    }
+    /// Handles drawing outside the clip rectangle, which is only an error in strict mode.
+    fn outside(&self, x: i16, y: i16) -> Result<(), Error<DI::Error>> {
+        if self.strict {
+            Err(Error::OutsideScreenAccess { x, y })
+        } else {
+            Ok(())
+        }
+    }
+    /// Clips a `w` by `h` area at (x, y) to the clip rectangle. In strict mode, an area that
+    /// doesn't fit is an error.
+    fn clip_area(&self, x: i16, y: i16, w: i16, h: i16) -> Result<Rect, Error<DI::Error>> {
+        let area = Rect::new(x, y, w, h);
+        if area.is_empty() {
+            return Ok(area);
+        }
+        let clipped = area.intersection(&self.clip);
+        if self.strict && clipped != area {
+            if self.clip.contains(x, y) {
+                return Err(Error::OutsideScreenAccess { x: x.saturating_add(w - 1), y: y.saturating_add(h - 1) });
+            }
+            return Err(Error::OutsideScreenAccess { x, y });
+        }
+        Ok(clipped)
+    }
 
-    pub fn start_scroll_right(&self, start: u8, stop: u8) {}
-    pub fn start_scroll_left(&self, start: u8, stop: u8) {}
-    pub fn start_scroll_diag_right(&self, start: u8, stop: u8) {}
-    pub fn start_scroll_diag_left(&self, start: u8, stop: u8) {}
-    pub fn stop_scroll(&self) {}
-    pub fn ssd1306_command(&mut self, command: &[u8]) -> Result<(), Error<I2C::Error>> {
-        self.i2c.transaction(self.address, &mut [Operation::Write(&[0x00]), Operation::Write(command)])?;
+    /// Continuously scrolls pages `start` to `stop` (inclusive) to the right, one column every `speed` frames.
+    pub fn start_scroll_right(&mut self, start: u8, stop: u8, speed: ScrollSpeed) -> Result<(), Error<DI::Error>> {
+        self.start_scroll_horizontal(RIGHT_HORIZONTAL_SCROLL, start, stop, speed)
+    }
+    /// Continuously scrolls pages `start` to `stop` (inclusive) to the left, one column every `speed` frames.
+    pub fn start_scroll_left(&mut self, start: u8, stop: u8, speed: ScrollSpeed) -> Result<(), Error<DI::Error>> {
+        self.start_scroll_horizontal(LEFT_HORIZONTAL_SCROLL, start, stop, speed)
+    }
+    /// Scrolls pages `start` to `stop` to the right while moving the vertical scroll area up by
+    /// `vertical_offset` rows each step. The top `fixed_rows` rows stay put and the following
+    /// `scroll_rows` rows scroll vertically.
+    pub fn start_scroll_diag_right(&mut self, start: u8, stop: u8, speed: ScrollSpeed, vertical_offset: u8, fixed_rows: u8, scroll_rows: u8) -> Result<(), Error<DI::Error>> {
+        let command = Self::diagonal_scroll_command(VERTICAL_AND_RIGHT_HORIZONTAL_SCROLL, start, stop, speed, vertical_offset, fixed_rows, scroll_rows)?;
+        self.ssd1306_command(&command)
+    }
+    /// Same as `start_scroll_diag_right`, but scrolling to the left.
+    pub fn start_scroll_diag_left(&mut self, start: u8, stop: u8, speed: ScrollSpeed, vertical_offset: u8, fixed_rows: u8, scroll_rows: u8) -> Result<(), Error<DI::Error>> {
+        let command = Self::diagonal_scroll_command(VERTICAL_AND_LEFT_HORIZONTAL_SCROLL, start, stop, speed, vertical_offset, fixed_rows, scroll_rows)?;
+        self.ssd1306_command(&command)
+    }
+    /// Stops any active scroll. The display RAM is left in its scrolled state, so the whole
+    /// buffer is marked dirty to be sent again by the next `flush`.
+    pub fn stop_scroll(&mut self) -> Result<(), Error<DI::Error>> {
+        self.resend_all();
+        self.ssd1306_command(&[DEACTIVATE_SCROLL])
+    }
+    fn start_scroll_horizontal(&mut self, direction: u8, start: u8, stop: u8, speed: ScrollSpeed) -> Result<(), Error<DI::Error>> {
+        Self::check_scroll_pages(start, stop)?;
+        // Scrolling has to be deactivated before the scroll parameters are changed
+        self.ssd1306_command(&[DEACTIVATE_SCROLL, direction, 0x00, start, speed as u8, stop, 0x00, 0xFF, ACTIVATE_SCROLL])
+    }
+    fn diagonal_scroll_command(direction: u8, start: u8, stop: u8, speed: ScrollSpeed, vertical_offset: u8, fixed_rows: u8, scroll_rows: u8) -> Result<[u8; 11], Error<DI::Error>> {
+        Self::check_scroll_pages(start, stop)?;
+        if fixed_rows as u16 + scroll_rows as u16 > SIZE::HEIGHT as u16 || vertical_offset >= scroll_rows {
+            return Err(Error::InvalidScrollRange);
+        }
+        Ok([
+            DEACTIVATE_SCROLL,
+            SET_VERTICAL_SCROLL_AREA, fixed_rows, scroll_rows,
+            direction, 0x00, start, speed as u8, stop, vertical_offset,
+            ACTIVATE_SCROLL,
+        ])
+    }
+    fn check_scroll_pages(start: u8, stop: u8) -> Result<(), Error<DI::Error>> {
+        if start > stop || stop >= SIZE::PAGES {
+            return Err(Error::InvalidScrollRange);
+        }
         Ok(())
     }
-    // pub fn get_pixel(&self, x: i16, y: i16) -> bool {
-    //
-    // }
-    // pub fn get_buffer(&mut self) -> &[u8] {
-    //
-    // }
-    pub fn draw_line(&mut self, x0: u16, y0: u16, x1: u16, y1: u16, color: u8) -> Result<(), Error<I2C::Error>> {
-        let mut x0 = x0 as i16;
-        let mut y0 = y0 as i16;
-        let mut x1 = x1 as i16;
-        let mut y1 = y1 as i16;
-        let steep = (y1 as i16 - y0 as i16).abs() > (x1 as i16 - x0 as i16).abs();
+    pub fn ssd1306_command(&mut self, command: &[u8]) -> Result<(), Error<DI::Error>> {
+        self.interface.write_command(command)
+    }
+    /// Draws a line with Bresenham's algorithm. Only the part inside the clip rectangle is walked,
+    /// so far off-screen endpoints are cheap.
+    pub fn draw_line(&mut self, x0: i16, y0: i16, x1: i16, y1: i16, color: u8) -> Result<(), Error<DI::Error>> {
+        // i32, since the distance between two i16 coordinates doesn't fit an i16
+        let mut x0 = x0 as i32;
+        let mut y0 = y0 as i32;
+        let mut x1 = x1 as i32;
+        let mut y1 = y1 as i32;
+        let steep = (y1 - y0).abs() > (x1 - x0).abs();
         if steep {
             swap(&mut x0, &mut y0);
             swap(&mut x1, &mut y1);
@@ -171,136 +694,1716 @@ impl<'buffer, I2C: I2c> DisplayDriver<'buffer, I2C> {
         }
 
         let dx = x1 - x0;
-        let dy = (y1 as i16 - y0 as i16).abs();
-        let mut err = dx / 2;
-        let y_step: i16 = if y0 < y1 { 1 } else { -1 };
+        let dy = (y1 - y0).abs();
+        let y_step: i32 = if y0 < y1 { 1 } else { -1 };
 
-        let mut x_runner = x0;
-        let mut y_runner = y0;
-        while x_runner <= x1 {
+        // In strict mode the whole line is walked, so that the first pixel outside fails
+        let (first, last) = if self.strict {
+            (x0, x1)
+        } else {
+            let (clip_start, clip_length) = if steep { (self.clip.y, self.clip.h) } else { (self.clip.x, self.clip.w) };
+            (x0.max(clip_start as i32), x1.min(clip_start as i32 + clip_length as i32 - 1))
+        };
+        if first > last {
+            return Ok(());
+        }
+        // Start at `first` as if the skipped pixels had been walked. The error term stays in
+        // 0..dx, which makes the number of y steps taken so far a rounded up division.
+        let skipped = (first - x0) as i64;
+        let behind = skipped * dy as i64 - (dx / 2) as i64;
+        let y_steps = if behind > 0 { (behind + dx as i64 - 1) / dx as i64 } else { 0 };
+        let mut err = (y_steps * dx as i64 - behind) as i32;
+        let mut y_runner = y0 + y_steps as i32 * y_step;
+
+        for x_runner in first..=last {
+            // Both coordinates lie between the i16 endpoints
             if steep {
-                self.draw_pixel(y_runner as u16, x_runner as u16, color)?;
+                self.draw_pixel(y_runner as i16, x_runner as i16, color)?;
             } else {
-                self.draw_pixel(x_runner as u16, y_runner as u16, color)?;
+                self.draw_pixel(x_runner as i16, y_runner as i16, color)?;
             }
             err -= dy;
             if err < 0 {
                 y_runner += y_step;
                 err += dx;
             }
-            x_runner += 1;
         }
         Ok(())
     }
-    pub fn draw_fast_h_line(&mut self, x: u16, y: u16, w: u16, color: u8) -> Result<(), Error<I2C::Error>> {
-        if x >= LCDWIDTH || y >= LCDHEIGHT {
-            return Err(Error::OutsideScreenAccess { x: x as i16, y: y as i16 });
-        } else if x + w >= LCDWIDTH {
-            return Err(Error::OutsideScreenAccess { x: (x + w) as i16, y: y as i16 });
+    /// Draws `w` pixels to the right of and including (x, y).
+    pub fn draw_fast_h_line(&mut self, x: i16, y: i16, w: i16, color: u8) -> Result<(), Error<DI::Error>> {
+        let line = self.clip_area(x, y, w, 1)?;
+        self.fill_physical(self.area_to_physical(line), color);
+        Ok(())
+    }
+    /// Draws `h` pixels downwards from and including (x, y).
+    pub fn draw_fast_v_line(&mut self, x: i16, y: i16, h: i16, color: u8) -> Result<(), Error<DI::Error>> {
+        let line = self.clip_area(x, y, 1, h)?;
+        self.fill_physical(self.area_to_physical(line), color);
+        Ok(())
+    }
+    pub fn draw_fill_rect(&mut self, x: i16, y: i16, w: i16, h: i16, color: u8) -> Result<(), Error<DI::Error>> {
+        let area = self.clip_area(x, y, w, h)?;
+        self.fill_physical(self.area_to_physical(area), color);
+        Ok(())
+    }
+    /// Fills an area of panel columns and rows that is already clipped.
+    fn fill_physical(&mut self, area: Rect, color: u8) {
+        if area.is_empty() {
+            return;
+        }
+        // Each column touches each page in its span once, so write a whole byte mask per page
+        let y_end = area.y + area.h;
+        for column in area.x..area.x + area.w {
+            let mut y_runner = area.y;
+            while y_runner < y_end {
+                let first_bit = y_runner % 8;
+                let bits = (8 - first_bit).min(y_end - y_runner);
+                let byte = ((0xFFu16 >> (8 - bits)) << first_bit) as u8;
+                let byte_index = SIZE::PAGES as usize * column as usize + (y_runner as usize / 8);
+                self.write_byte(byte_index, byte, color);
+                y_runner += bits;
+            }
         }
-        // A faster implementation can be added here
-        self.draw_line(x, y, x + w, y, color)
     }
-    pub fn draw_fast_v_line(&mut self, x: u16, y: u16, h: u16, color: u8) -> Result<(), Error<I2C::Error>> {
-        if x >= LCDWIDTH || y >= LCDHEIGHT {
-            return Err(Error::OutsideScreenAccess { x: x as i16, y: y as i16 });
-        } else if y + h >= LCDHEIGHT {
-            return Err(Error::OutsideScreenAccess { x: x as i16, y: (y + h) as i16 });
+    pub fn fill_screen(&mut self, color: u8) {
+        self.mark_all_dirty();
+        if color != 0 {
+            self.buffer.as_mut().fill(0xFF);
+        } else {
+            self.buffer.as_mut().fill(0x00);
         }
-        // A faster implementation can be added here
-        self.draw_line(x, y, x, y + h, color)
     }
-    pub fn draw_fill_rect(&mut self, x: u16, y: u16, w: u16, h: u16, color: u8) -> Result<(), Error<I2C::Error>> {
-        for i in x..x + w {
-            self.draw_fast_v_line(i, y, h, color)?
+    pub fn fill_screen_byte(&mut self, byte: u8) {
+        self.mark_all_dirty();
+        self.buffer.as_mut().fill(byte);
+    }
+    /// Draws the outline of a `w` by `h` rectangle. Corners are only drawn once, so INVERSE works.
+    pub fn draw_rect(&mut self, x: i16, y: i16, w: i16, h: i16, color: u8) -> Result<(), Error<DI::Error>> {
+        if w <= 0 || h <= 0 {
+            return Ok(());
+        }
+        let (x, y, w, h) = (x as i32, y as i32, w as i32, h as i32);
+        self.draw_wide_h_line(x, y, w, color)?;
+        if h > 1 {
+            self.draw_wide_h_line(x, y + h - 1, w, color)?;
+        }
+        if h > 2 {
+            self.draw_wide_v_line(x, y + 1, h - 2, color)?;
+            if w > 1 {
+                self.draw_wide_v_line(x + w - 1, y + 1, h - 2, color)?;
+            }
         }
         Ok(())
     }
-    pub fn fill_screen(&mut self, color: u8) {
-        if color != 0 {
-            for i in 0..BUFFER_SIZE {
-                self.buffer[i] = 0xFF;
+    /// Draws the outline of a circle centered on (x0, y0), using the midpoint circle algorithm.
+    pub fn draw_circle(&mut self, x0: i16, y0: i16, r: i16, color: u8) -> Result<(), Error<DI::Error>> {
+        if r < 0 {
+            return Ok(());
+        }
+        let (x0, y0, r) = (x0 as i32, y0 as i32, r as i32);
+        self.draw_wide_pixel(x0, y0 + r, color)?;
+        if r == 0 {
+            return Ok(());
+        }
+        self.draw_wide_pixel(x0, y0 - r, color)?;
+        self.draw_wide_pixel(x0 + r, y0, color)?;
+        self.draw_wide_pixel(x0 - r, y0, color)?;
+        self.draw_circle_arcs(x0, y0, r, 0x0F, color)
+    }
+    /// Draws the quarter circle arcs selected by the bits in `corners`, excluding the points on
+    /// the axes. Bit 0 is the top left quarter, bit 1 top right, bit 2 bottom right and bit 3
+    /// bottom left.
+    pub fn draw_circle_helper(&mut self, x0: i16, y0: i16, r: i16, corners: u8, color: u8) -> Result<(), Error<DI::Error>> {
+        self.draw_circle_arcs(x0 as i32, y0 as i32, r as i32, corners, color)
+    }
+    fn draw_circle_arcs(&mut self, x0: i32, y0: i32, r: i32, corners: u8, color: u8) -> Result<(), Error<DI::Error>> {
+        let mut f = 1 - r;
+        let mut dd_f_x = 1;
+        let mut dd_f_y = -2 * r;
+        let mut x = 0;
+        let mut y = r;
+
+        while x < y {
+            if f >= 0 {
+                y -= 1;
+                dd_f_y += 2;
+                f += dd_f_y;
             }
-        } else {
-            for i in 0..BUFFER_SIZE {
-                self.buffer[i] = 0x00;
+            x += 1;
+            dd_f_x += 2;
+            f += dd_f_x;
+            if x > y {
+                // Stepped past the diagonal, these pixels have already been drawn
+                break;
+            }
+            // On the diagonal both points of an octant pair are the same pixel
+            let diagonal = x == y;
+            if corners & 0x4 != 0 {
+                self.draw_wide_pixel(x0 + x, y0 + y, color)?;
+                if !diagonal {
+                    self.draw_wide_pixel(x0 + y, y0 + x, color)?;
+                }
+            }
+            if corners & 0x2 != 0 {
+                self.draw_wide_pixel(x0 + x, y0 - y, color)?;
+                if !diagonal {
+                    self.draw_wide_pixel(x0 + y, y0 - x, color)?;
+                }
+            }
+            if corners & 0x8 != 0 {
+                self.draw_wide_pixel(x0 - x, y0 + y, color)?;
+                if !diagonal {
+                    self.draw_wide_pixel(x0 - y, y0 + x, color)?;
+                }
+            }
+            if corners & 0x1 != 0 {
+                self.draw_wide_pixel(x0 - x, y0 - y, color)?;
+                if !diagonal {
+                    self.draw_wide_pixel(x0 - y, y0 - x, color)?;
+                }
+            }
+        }
+        Ok(())
+    }
+    pub fn fill_circle(&mut self, x0: i16, y0: i16, r: i16, color: u8) -> Result<(), Error<DI::Error>> {
+        if r < 0 {
+            return Ok(());
+        }
+        let (x0, y0, r) = (x0 as i32, y0 as i32, r as i32);
+        self.draw_wide_v_line(x0, y0 - r, 2 * r + 1, color)?;
+        self.fill_circle_halves(x0, y0, r, 0x3, 0, color)
+    }
+    /// Fills the right (bit 0 of `corners`) and/or left (bit 1) half of a circle, excluding the
+    /// center column, with the halves stretched downwards by `delta` rows. Every pixel is drawn
+    /// once, so INVERSE works.
+    pub fn fill_circle_helper(&mut self, x0: i16, y0: i16, r: i16, corners: u8, delta: i16, color: u8) -> Result<(), Error<DI::Error>> {
+        self.fill_circle_halves(x0 as i32, y0 as i32, r as i32, corners, delta as i32, color)
+    }
+    fn fill_circle_halves(&mut self, x0: i32, y0: i32, r: i32, corners: u8, delta: i32, color: u8) -> Result<(), Error<DI::Error>> {
+        let mut f = 1 - r;
+        let mut dd_f_x = 1;
+        let mut dd_f_y = -2 * r;
+        let mut x = 0;
+        let mut y = r;
+        let mut px = x;
+        let mut py = y;
+        let delta = delta + 1;
+
+        while x < y {
+            if f >= 0 {
+                y -= 1;
+                dd_f_y += 2;
+                f += dd_f_y;
+            }
+            x += 1;
+            dd_f_x += 2;
+            f += dd_f_x;
+            // Only draw each column once, even when x and y step past each other
+            if x < y + 1 {
+                if corners & 0x1 != 0 {
+                    self.draw_wide_v_line(x0 + x, y0 - y, 2 * y + delta, color)?;
+                }
+                if corners & 0x2 != 0 {
+                    self.draw_wide_v_line(x0 - x, y0 - y, 2 * y + delta, color)?;
+                }
+            }
+            if y != py {
+                if corners & 0x1 != 0 {
+                    self.draw_wide_v_line(x0 + py, y0 - px, 2 * px + delta, color)?;
+                }
+                if corners & 0x2 != 0 {
+                    self.draw_wide_v_line(x0 - py, y0 - px, 2 * px + delta, color)?;
+                }
+                py = y;
             }
+            px = x;
         }
+        Ok(())
     }
-    pub fn fill_screen_byte(&mut self, byte: u8) {
-        for i in 0..BUFFER_SIZE {
-            self.buffer[i] = byte;
+    /// Draws the outline of a rectangle with corners of radius `r`, limited to half the shortest side.
+    pub fn draw_round_rect(&mut self, x: i16, y: i16, w: i16, h: i16, r: i16, color: u8) -> Result<(), Error<DI::Error>> {
+        let r = r.min(w.min(h) / 2);
+        if r <= 0 {
+            return self.draw_rect(x, y, w, h, color);
+        }
+        let (x, y, w, h, r) = (x as i32, y as i32, w as i32, h as i32, r as i32);
+        self.draw_wide_h_line(x + r, y, w - 2 * r, color)?;
+        self.draw_wide_h_line(x + r, y + h - 1, w - 2 * r, color)?;
+        self.draw_wide_v_line(x, y + r, h - 2 * r, color)?;
+        self.draw_wide_v_line(x + w - 1, y + r, h - 2 * r, color)?;
+        self.draw_circle_arcs(x + r, y + r, r, 0x1, color)?;
+        self.draw_circle_arcs(x + w - r - 1, y + r, r, 0x2, color)?;
+        self.draw_circle_arcs(x + w - r - 1, y + h - r - 1, r, 0x4, color)?;
+        self.draw_circle_arcs(x + r, y + h - r - 1, r, 0x8, color)
+    }
+    pub fn fill_round_rect(&mut self, x: i16, y: i16, w: i16, h: i16, r: i16, color: u8) -> Result<(), Error<DI::Error>> {
+        let r = r.min(w.min(h) / 2);
+        if r <= 0 {
+            return self.draw_fill_rect(x, y, w, h, color);
+        }
+        let (x, y, w, h, r) = (x as i32, y as i32, w as i32, h as i32, r as i32);
+        self.draw_wide_fill_rect(x + r, y, w - 2 * r, h, color)?;
+        self.fill_circle_halves(x + w - r - 1, y + r, r, 0x1, h - 2 * r - 1, color)?;
+        self.fill_circle_halves(x + r, y + r, r, 0x2, h - 2 * r - 1, color)
+    }
+    pub fn draw_triangle(&mut self, p0: (i16, i16), p1: (i16, i16), p2: (i16, i16), color: u8) -> Result<(), Error<DI::Error>> {
+        self.draw_line(p0.0, p0.1, p1.0, p1.1, color)?;
+        self.draw_line(p1.0, p1.1, p2.0, p2.1, color)?;
+        self.draw_line(p2.0, p2.1, p0.0, p0.1, color)
+    }
+    /// Fills a triangle with horizontal spans, top to bottom.
+    pub fn fill_triangle(&mut self, p0: (i16, i16), p1: (i16, i16), p2: (i16, i16), color: u8) -> Result<(), Error<DI::Error>> {
+        // i32, since the distance between two i16 coordinates doesn't fit an i16
+        let (mut x0, mut y0) = (p0.0 as i32, p0.1 as i32);
+        let (mut x1, mut y1) = (p1.0 as i32, p1.1 as i32);
+        let (mut x2, mut y2) = (p2.0 as i32, p2.1 as i32);
+        // Sort the corners by y, so that y0 <= y1 <= y2
+        if y0 > y1 {
+            swap(&mut y0, &mut y1);
+            swap(&mut x0, &mut x1);
+        }
+        if y1 > y2 {
+            swap(&mut y2, &mut y1);
+            swap(&mut x2, &mut x1);
+        }
+        if y0 > y1 {
+            swap(&mut y0, &mut y1);
+            swap(&mut x0, &mut x1);
+        }
+
+        if y0 == y2 {
+            // All corners on the same row
+            let a = x0.min(x1).min(x2);
+            let b = x0.max(x1).max(x2);
+            return self.draw_wide_h_line(a, y0, b - a + 1, color);
+        }
+
+        let dx01 = x1 - x0;
+        let dy01 = y1 - y0;
+        let dx02 = x2 - x0;
+        let dy02 = y2 - y0;
+        let dx12 = x2 - x1;
+        let dy12 = y2 - y1;
+        let mut sa: i32 = 0;
+        let mut sb: i32 = 0;
+
+        // The upper part goes from y0 to y1, including y1 only if the lower part is flat
+        let last = if y1 == y2 { y1 } else { y1 - 1 };
+        let mut y = y0;
+        while y <= last {
+            let a = x0 + sa / dy01;
+            let b = x0 + sb / dy02;
+            sa += dx01;
+            sb += dx02;
+            self.draw_wide_h_line(a.min(b), y, (a - b).abs() + 1, color)?;
+            y += 1;
+        }
+
+        // The lower part goes from y1 to y2
+        sa = dx12 * (y - y1);
+        sb = dx02 * (y - y0);
+        while y <= y2 {
+            let a = x1 + sa / dy12;
+            let b = x0 + sb / dy02;
+            sa += dx12;
+            sb += dx02;
+            self.draw_wide_h_line(a.min(b), y, (a - b).abs() + 1, color)?;
+            y += 1;
+        }
+        Ok(())
+    }
+    /// `draw_pixel` for shapes whose coordinates may not fit an i16.
+    fn draw_wide_pixel(&mut self, x: i32, y: i32, color: u8) -> Result<(), Error<DI::Error>> {
+        self.draw_pixel(saturate(x), saturate(y), color)
+    }
+    /// `draw_fast_h_line` for shapes whose coordinates may not fit an i16.
+    fn draw_wide_h_line(&mut self, x: i32, y: i32, w: i32, color: u8) -> Result<(), Error<DI::Error>> {
+        self.draw_wide_fill_rect(x, y, w, 1, color)
+    }
+    /// `draw_fast_v_line` for shapes whose coordinates may not fit an i16.
+    fn draw_wide_v_line(&mut self, x: i32, y: i32, h: i32, color: u8) -> Result<(), Error<DI::Error>> {
+        self.draw_wide_fill_rect(x, y, 1, h, color)
+    }
+    /// `draw_fill_rect` for shapes whose coordinates may not fit an i16.
+    fn draw_wide_fill_rect(&mut self, x: i32, y: i32, w: i32, h: i32, color: u8) -> Result<(), Error<DI::Error>> {
+        if w <= 0 || h <= 0 {
+            return Ok(());
+        }
+        let (left, right) = clamp_span(x, x + w - 1, self.clip.x, self.clip.w);
+        let (top, bottom) = clamp_span(y, y + h - 1, self.clip.y, self.clip.h);
+        self.draw_fill_rect(left, top, right - left + 1, bottom - top + 1, color)
+    }
+
+
+    /// Draws the set pixels of `bitmap` with `color`, leaving the others transparent. With a
+    /// `mask`, only the pixels set in the mask are drawn, the clear bitmap pixels in the
+    /// opposite color (or left alone for INVERSE). Page aligned `PageMajor` bitmaps that are
+    /// fully visible are copied byte by byte.
+    pub fn draw_bitmap(&mut self, x: i16, y: i16, bitmap: &Bitmap, mask: Option<&Bitmap>, color: u8) -> Result<(), Error<DI::Error>> {
+        if bitmap.width <= 0 || bitmap.height <= 0 {
+            return Ok(());
+        }
+        let background = match color {
+            WHITE => Some(BLACK),
+            BLACK => Some(WHITE),
+            _ => None,
+        };
+        let page_major = bitmap.format == BitmapFormat::PageMajor
+            && mask.is_none_or(|mask| mask.format == BitmapFormat::PageMajor);
+        let visible = self.clip.contains(x, y)
+            && self.clip.contains(x.saturating_add(bitmap.width - 1), y.saturating_add(bitmap.height - 1));
+        if page_major && visible && y % 8 == 0 && self.config.rotation == Rotation::Rotate0 {
+            let pages = (bitmap.height + 7) / 8;
+            for column in 0..bitmap.width {
+                for page in 0..pages {
+                    let rows = if page == pages - 1 && bitmap.height % 8 != 0 {
+                        (1 << (bitmap.height % 8)) - 1
+                    } else {
+                        0xFF
+                    };
+                    let bits = bitmap.page_byte(column, page) & rows;
+                    let mask_bits = mask.map_or(bits, |mask| mask.page_byte(column, page) & rows);
+                    let byte_index = SIZE::PAGES as usize * (x + column) as usize + (y / 8 + page) as usize;
+                    self.write_byte(byte_index, bits & mask_bits, color);
+                    if let Some(background) = background.filter(|_| !bits & mask_bits != 0) {
+                        self.write_byte(byte_index, !bits & mask_bits, background);
+                    }
+                }
+            }
+            return Ok(());
+        }
+        for row in 0..bitmap.height {
+            for column in 0..bitmap.width {
+                let set = bitmap.is_set(column, row);
+                if !mask.map_or(set, |mask| mask.is_set(column, row)) {
+                    continue;
+                }
+                let color = if set { Some(color) } else { background };
+                if let Some(color) = color {
+                    self.draw_pixel(x.saturating_add(column), y.saturating_add(row), color)?;
+                }
+            }
+        }
+        Ok(())
+    }
+    pub fn draw_string(&mut self, text: &str) -> Result<(), Error<DI::Error>> {
+        for character in text.chars() {
+            self.draw_char(character)?;
+        }
+        Ok(())
+    }
+    /// Width and height in pixels of `text` drawn with `draw_string`, without wrapping.
+    pub fn measure_text(&self, text: &str) -> (i16, i16) {
+        if text.is_empty() {
+            return (0, 0);
         }
+        let mut width = 0;
+        let mut lines: i32 = 0;
+        for line in text.split('\n') {
+            width = width.max(self.line_width(line));
+            lines += 1;
+        }
+        let scale = self.text_scale as i32;
+        let height = (lines - 1) * self.font.line_height as i32 * scale + self.font.height as i32 * scale;
+        (width, saturate(height))
     }
-    pub fn draw_rect(x: i16, y: i16, w: i16, h: i16, color: u16) {}
-    pub fn draw_circle(x0: i16, y0: i16, r: i16, color: u16) {}
-    pub fn draw_circle_helper(x0: i16, y0: i16, r: i16, corner_name: u8, color: u16) {}
-    pub fn fill_circle(x0: i16, y0: i16, r: i16, color: u16) {}
-    pub fn fill_circle_helper(x0: i16, y0: i16, r: i16, corner_name: u8, color: u16) {}
+    /// Draws `text` inside `area`, wrapping at spaces and placing each line according to `align`.
+    /// Lines that don't fit completely are left out. Returns how many bytes of `text` were drawn,
+    /// which is `text.len()` unless it overflowed, so the rest can be shown on another page.
+    pub fn draw_text_box(&mut self, text: &str, area: Rect, align: Align, overflow: Overflow, color: u8) -> Result<usize, Error<DI::Error>> {
+        let scale = self.text_scale as i16;
+        let line_height = self.font.line_height as i32 * scale as i32;
+        let glyph_height = self.font.height as i32 * scale as i32;
+        let bottom = area.y as i32 + area.h as i32;
+        let saved_clip = self.clip;
+        self.clip = self.clip.intersection(&area);
 
+        let mut result = Ok(());
+        let mut start = 0;
+        let mut y = area.y as i32;
+        while start < text.len() && y + glyph_height <= bottom {
+            let (mut line, next) = self.wrap_line(&text[start..], area.w);
+            let is_last = y + line_height + glyph_height > bottom;
+            let mut drawn = next;
+            let mut width = self.line_width(line);
+            let mut ellipsis = false;
+            if is_last && start + next < text.len() && overflow == Overflow::Ellipsis {
+                let ellipsis_width = self.line_width("...");
+                while !line.is_empty() && self.text_advance(line) + ellipsis_width > area.w {
+                    line = &line[..line.char_indices().last().map_or(0, |(index, _)| index)];
+                }
+                line = line.trim_end_matches(' ');
+                drawn = line.len();
+                width = self.text_advance(line) + ellipsis_width;
+                ellipsis = true;
+            }
+            let x = match align {
+                Align::Left => area.x,
+                Align::Center => area.x + (area.w - width) / 2,
+                Align::Right => area.x + area.w - width,
+            };
+            result = self.draw_text_run(x, y as i16, line, color);
+            if result.is_ok() && ellipsis {
+                let x = x + self.text_advance(line);
+                result = self.draw_text_run(x, y as i16, "...", color);
+            }
+            start += drawn;
+            if result.is_err() || ellipsis {
+                break;
+            }
+            y += line_height;
+        }
 
-    pub fn read(&mut self) -> Result<u8, I2C::Error> {
-        let mut temp = [0];
-        self.i2c.write_read(DEFAULT_ADDRESS, &[TEMP_REGISTER], &mut temp)?;
-        Ok(temp[0])
+        self.clip = saved_clip;
+        result?;
+        Ok(start)
     }
-    pub fn draw_string(&mut self, text: &str) {
+    /// Draws `text` on one line starting at (x, y).
+    fn draw_text_run(&mut self, x: i16, y: i16, text: &str, color: u8) -> Result<(), Error<DI::Error>> {
+        let mut x = x;
         for character in text.chars() {
-            // println!("print {}", character);
-            self.draw_char(character);
+            if character != '\r' {
+                self.draw_char_at(x, y, character, color)?;
+                x = x.saturating_add(self.char_advance(character));
+            }
+        }
+        Ok(())
+    }
+    /// Splits off the first line of `text` that fits in `width` pixels, breaking after the last
+    /// space when possible. Returns the line and the index the next line starts at.
+    fn wrap_line<'t>(&self, text: &'t str, width: i16) -> (&'t str, usize) {
+        let spacing = self.font.spacing as i16 * self.text_scale as i16;
+        let mut advance: i16 = 0;
+        let mut last_space = None;
+        for (index, character) in text.char_indices() {
+            match character {
+                '\n' => return (&text[..index], index + 1),
+                ' ' => last_space = Some(index),
+                _ => {}
+            }
+            advance = advance.saturating_add(self.char_advance(character));
+            // Always keep at least one character, so glyphs wider than the box still make progress
+            if character != ' ' && index > 0 && advance - spacing > width {
+                return match last_space {
+                    Some(space) => (text[..space].trim_end_matches(' '), space + 1),
+                    None => (&text[..index], index),
+                };
+            }
+        }
+        (text, text.len())
+    }
+    /// Width of `text` on one line, without the spacing after the last glyph.
+    fn line_width(&self, text: &str) -> i16 {
+        let advance = self.text_advance(text);
+        if advance == 0 {
+            return 0;
         }
+        advance - self.font.spacing as i16 * self.text_scale as i16
     }
-    pub fn draw_char(&mut self, character: char) -> Result<(), Error<I2C::Error>> {
+    fn text_advance(&self, text: &str) -> i16 {
+        text.chars().fold(0, |advance: i16, character| advance.saturating_add(self.char_advance(character)))
+    }
+    fn char_advance(&self, character: char) -> i16 {
+        if character == '\r' {
+            return 0;
+        }
+        self.char_code(character).map_or(0, |code| self.font.advance(code) as i16 * self.text_scale as i16)
+    }
+    pub fn draw_char(&mut self, character: char) -> Result<(), Error<DI::Error>> {
+        let line_height = self.font.line_height as i16 * self.text_scale as i16;
         if character == '\n' {
             self.cursor_x = 0;
-            self.cursor_y += FONT_HEIGHT_1;
+            self.cursor_y += line_height;
         } else if character != '\r' {
-            if self.cursor_x + FONT_WIDTH_1 > LCDWIDTH {
+            let advance = self.font.advance(self.char_code(character)?) as i16 * self.text_scale as i16;
+            if self.cursor_x + advance > self.width() {
                 self.cursor_x = 0;
-                self.cursor_y += FONT_HEIGHT_1;
+                self.cursor_y += line_height;
+            }
+            if self.text_scroll {
+                self.scroll_to_cursor();
             }
             self.draw_char_at(self.cursor_x, self.cursor_y, character, WHITE)?;
-            self.cursor_x += FONT_WIDTH_1;
+            self.cursor_x += advance;
         }
         Ok(())
     }
-    pub fn draw_char_at(&mut self, x: u16, y: u16, character: char, color: u8) -> Result<(), Error<I2C::Error>> {
-        // println!("draw_char_at");
-        if (x + FONT_WIDTH >= LCDWIDTH)
-            || (y + FONT_HEIGHT >= LCDHEIGHT)
-        // || (x + FONT_WIDTH) < 0
-        // || (y + FONT_HEIGHT) < 0
-        {
-            return Err(Error::OutsideScreenAccess {
-                x: 0,
-                y: 0,
-            });
-        }
-
-        // println!("{}, {}, {}", character, x, y);
-        if character.is_ascii() {
-            let encoded = character as u8;
-        // }
-        // if let Some(encoded) = CP437_CONTROL.encode(character) {
-            for i in 0..FONT_WIDTH {
-                // println!("{}, {}", x, i);
-                if let Some(line) = FONT.get(encoded as usize * FONT_WIDTH as usize + i as usize) {
-                    let mut line = *line;
-                    for j in 0..8 {
-                        if line & 1 != 0 {
-                            self.draw_pixel(x + i, y + j, color)?;
-                        }
-                        line >>= 1;
+    /// Draws a glyph of the current font with its top left corner at (x, y), each pixel scaled
+    /// to a square of `text_scale` pixels. Pixels outside the clip rectangle are skipped, so
+    /// glyphs can be partially visible.
+    pub fn draw_char_at(&mut self, x: i16, y: i16, character: char, color: u8) -> Result<(), Error<DI::Error>> {
+        let font = self.font;
+        let Some(glyph) = font.glyph(self.char_code(character)?) else {
+            return Err(Error::InvalidChar(character));
+        };
+        let scale = self.text_scale as i16;
+        for column in 0..glyph.width {
+            for row in 0..font.height {
+                if font.is_set(&glyph, column, row) {
+                    let pixel_x = x + column as i16 * scale;
+                    let pixel_y = y + row as i16 * scale;
+                    if scale == 1 {
+                        self.draw_pixel(pixel_x, pixel_y, color)?;
+                    } else {
+                        self.draw_fill_rect(pixel_x, pixel_y, scale, scale, color)?;
                     }
-                } else {
-                    return Err(Error::InvalidChar(character));
                 }
             }
         }
         Ok(())
     }
-    pub fn set_cursor(&mut self, x: u16, y: u16) {
-        self.cursor_x = x;
-        self.cursor_y = y;
+    /// The font code for `character`, or the replacement glyph if the font has no glyph for it.
+    fn char_code(&self, character: char) -> Result<u8, Error<DI::Error>> {
+        cp437(character)
+            .filter(|code| self.font.glyph(*code).is_some())
+            .or(self.replacement_char)
+            .ok_or(Error::InvalidChar(character))
+    }
+    /// Glyph drawn for characters the font doesn't have, `'?'` by default. With `None` they fail
+    /// with `Error::InvalidChar`.
+    pub fn set_replacement_char(&mut self, code: Option<u8>) {
+        self.replacement_char = code;
+    }
+    /// Font used by `draw_char` and `draw_char_at`. Defaults to `FONT_5X7`.
+    pub fn set_font(&mut self, font: &'static Font) {
+        self.font = font;
+    }
+    /// Draws every glyph pixel as a `scale` x `scale` square, 1 is the font's own size.
+    pub fn set_text_scale(&mut self, scale: u8) {
+        self.text_scale = scale.max(1);
+    }
+    /// When enabled, `draw_char` scrolls the buffer up by whole text lines instead of drawing
+    /// below the bottom of the screen, like a terminal.
+    pub fn set_text_scroll(&mut self, text_scroll: bool) {
+        self.text_scroll = text_scroll;
+    }
+    fn scroll_to_cursor(&mut self) {
+        let line_height = self.font.line_height as i16 * self.text_scale as i16;
+        let glyph_height = self.font.height as i16 * self.text_scale as i16;
+        while line_height > 0 && self.cursor_y > 0 && self.cursor_y + glyph_height > self.height() {
+            self.scroll_up(line_height);
+            self.cursor_y -= line_height;
+        }
+    }
+    /// Moves the whole buffer up by `rows` pixels, clearing the rows that come in at the bottom.
+    /// Only changes the buffer, unlike the hardware scrolling commands.
+    pub fn scroll_up(&mut self, rows: i16) {
+        self.mark_all_dirty();
+        if self.config.rotation != Rotation::Rotate0 {
+            return self.scroll_up_rotated(rows);
+        }
+        let pages = SIZE::PAGES as usize;
+        let rows = rows.clamp(0, SIZE::HEIGHT as i16) as usize;
+        let (page_shift, bit_shift) = (rows / 8, rows % 8);
+        for column in self.buffer.as_mut().chunks_exact_mut(pages) {
+            for page in 0..pages {
+                let low = column.get(page + page_shift).copied().unwrap_or(0);
+                let high = column.get(page + page_shift + 1).copied().unwrap_or(0);
+                column[page] = if bit_shift == 0 {
+                    low
+                } else {
+                    (low >> bit_shift) | (high << (8 - bit_shift))
+                };
+            }
+        }
+    }
+    /// Pixel by pixel, since canvas rows don't line up with the pages when rotated.
+    fn scroll_up_rotated(&mut self, rows: i16) {
+        let rows = rows.clamp(0, self.height());
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let set = y + rows < self.height() && self.get_pixel(x, y + rows);
+                let (column, row) = self.to_physical(x, y);
+                let byte_index = SIZE::PAGES as usize * column as usize + (row as usize / 8);
+                apply_color(&mut self.buffer.as_mut()[byte_index], 1 << (row % 8), if set { WHITE } else { BLACK });
+            }
+        }
+    }
+    pub fn set_cursor(&mut self, x: i16, y: i16) {
+        self.cursor_x = x;
+        self.cursor_y = y;
+    }
+}
+
+/// Writes at the text cursor with `draw_char`, so `uwrite!` can print to the display.
+impl<'buffer, DI: DisplayInterface, SIZE: DisplaySize> uWrite for DisplayDriver<'buffer, DI, SIZE> {
+    type Error = Error<DI::Error>;
+
+    fn write_str(&mut self, s: &str) -> Result<(), Self::Error> {
+        self.draw_string(s)
+    }
+
+    fn write_char(&mut self, c: char) -> Result<(), Self::Error> {
+        self.draw_char(c)
+    }
+}
+
+impl<'buffer, DI: DisplayInterface, SIZE: DisplaySize> core::fmt::Write for DisplayDriver<'buffer, DI, SIZE> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.draw_string(s).map_err(|_| core::fmt::Error)
+    }
+}
+
+/// Converts a coordinate to i16, where values that don't fit are off-screen anyway.
+fn saturate(value: i32) -> i16 {
+    value.clamp(i16::MIN as i32, i16::MAX as i32) as i16
+}
+
+/// Shortens the span from `first` to `last` to at most one pixel past each end of the clip
+/// rectangle's `start` and `length` on the same axis, which is clipped the same way.
+fn clamp_span(first: i32, last: i32, start: i16, length: i16) -> (i16, i16) {
+    let (low, high) = (start as i32 - 1, start as i32 + length as i32);
+    (first.clamp(low, high) as i16, last.clamp(low, high) as i16)
+}
+
+/// Applies `color` to the bits set in `mask`. WHITE sets them, BLACK clears them and INVERSE flips them.
+fn apply_color(byte: &mut u8, mask: u8, color: u8) {
+    match color {
+        WHITE => *byte |= mask,
+        BLACK => *byte &= !mask,
+        INVERSE => *byte ^= mask,
+        _ => {}
+    }
+}
+
+/// The charge pump is only needed when the panel voltage isn't supplied externally.
+fn charge_pump(vcc_state: u8) -> u8 {
+    if vcc_state == EXTERNALVCC { 0x10 } else { 0x14 }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+    use core::fmt::Write;
+    use core::convert::Infallible;
+    use embedded_hal::digital::{self, OutputPin};
+    use embedded_hal::i2c::ErrorKind;
+    use crate::ssd1306::{Align, DirtyArea, DisplayDriver, Overflow, Rect, ScrollSpeed};
+    use crate::ssd1306_bitmap::{Bitmap, BitmapFormat};
+    use crate::ssd1306_config::{Config, Rotation};
+    use crate::ssd1306_size::{DisplaySize, DisplaySize128x32, DisplaySize128x64, DisplaySize96x16};
+    use crate::ssd1306_error::Error;
+    use crate::ssd1306_font::{Font, GlyphWidths};
+    use crate::ssd1306_interface::{DisplayInterface, I2cInterface};
+    use crate::ssd1306_registers::{BLACK, EXTERNALVCC, INVERSE, WHITE};
+    use crate::test_support::{assert_golden, noop_display, render_ascii, render_pbm, NoopI2c, RecordingI2c};
+    use ufmt::uWrite;
+
+    /// Logs what an `I2cInterface` would put on the bus, prefixing commands with 0x00 and data
+    /// with 0x40, until the log is full.
+    struct RecordingInterface {
+        log: [u8; 128],
+        length: usize,
+    }
+
+    impl RecordingInterface {
+        fn new() -> Self {
+            Self { log: [0x00; 128], length: 0 }
+        }
+
+        fn written(&self) -> &[u8] {
+            &self.log[..self.length]
+        }
+
+        fn clear(&mut self) {
+            self.length = 0;
+        }
+
+        fn append(&mut self, bytes: &[u8]) {
+            let length = bytes.len().min(self.log.len() - self.length);
+            self.log[self.length..self.length + length].copy_from_slice(&bytes[..length]);
+            self.length += length;
+        }
+    }
+
+    impl DisplayInterface for RecordingInterface {
+        type Error = ErrorKind;
+
+        fn write_command(&mut self, command: &[u8]) -> Result<(), Error<Self::Error>> {
+            self.append(&[0x00]);
+            self.append(command);
+            Ok(())
+        }
+
+        fn write_data(&mut self, data: &[u8]) -> Result<(), Error<Self::Error>> {
+            self.append(&[0x40]);
+            self.append(data);
+            Ok(())
+        }
+    }
+
+    /// A 128x64 display with the default config that logs what it sends.
+    fn recording_display(buffer: &mut <DisplaySize128x64 as DisplaySize>::Buffer) -> DisplayDriver<'_, RecordingInterface> {
+        DisplayDriver::new(RecordingInterface::new(), buffer, DisplaySize128x64, Config::default()).unwrap()
+    }
+
+    #[test]
+    fn draw_pixel_color_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = noop_display(&mut buffer);
+            display.draw_pixel(1, 9, WHITE).unwrap();
+            display.draw_pixel(1, 10, WHITE).unwrap();
+            display.draw_pixel(1, 9, BLACK).unwrap();
+            display.draw_pixel(1, 11, INVERSE).unwrap();
+            display.draw_pixel(1, 10, INVERSE).unwrap();
+        }
+        assert_eq!(buffer[9], 0b0000_1000);
+        assert_eq!(buffer.iter().filter(|byte| **byte != 0).count(), 1);
+    }
+
+    #[test]
+    fn draw_line_color_test() {
+        let mut buffer = [0xFF; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = noop_display(&mut buffer);
+            display.draw_line(0, 0, 3, 0, BLACK).unwrap();
+            display.draw_line(0, 8, 0, 15, INVERSE).unwrap();
+        }
+        assert_eq!(&buffer[..9], &[0xFE, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFE]);
+        assert_eq!(&buffer[16..33], &[0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn draw_fast_lines_color_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = noop_display(&mut buffer);
+            display.draw_fast_v_line(0, 4, 8, WHITE).unwrap();
+            display.draw_fast_v_line(0, 6, 2, INVERSE).unwrap();
+            display.draw_fast_h_line(1, 2, 3, WHITE).unwrap();
+            display.draw_fast_h_line(2, 2, 1, BLACK).unwrap();
+        }
+        assert_eq!(&buffer[..2], &[0b0011_0000, 0b0000_1111]);
+        assert_eq!(buffer[8], 0b0000_0100);
+        assert_eq!(buffer[16], 0b0000_0000);
+        assert_eq!(buffer[24], 0b0000_0100);
+        assert_eq!(buffer.iter().filter(|byte| **byte != 0).count(), 4);
+    }
+
+    #[test]
+    fn draw_fill_rect_color_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = noop_display(&mut buffer);
+            display.draw_fill_rect(0, 0, 2, 16, WHITE).unwrap();
+            display.draw_fill_rect(1, 4, 2, 8, INVERSE).unwrap();
+            display.draw_fill_rect(0, 0, 1, 1, BLACK).unwrap();
+        }
+        // The buffer is column-major, each column being SIZE::PAGES page bytes
+        assert_eq!(&buffer[0..2], &[0xFE, 0xFF]);
+        assert_eq!(&buffer[8..10], &[0x0F, 0xF0]);
+        assert_eq!(&buffer[16..18], &[0xF0, 0x0F]);
+        assert_eq!(buffer.iter().filter(|byte| **byte != 0).count(), 6);
+    }
+
+    #[test]
+    fn draw_char_at_color_test() {
+        let mut buffer = [0xFF; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = noop_display(&mut buffer);
+            // 'I' is 0x00, 0x41, 0x7F, 0x41, 0x00 in the font
+            display.draw_char_at(0, 0, 'I', BLACK).unwrap();
+            display.draw_char_at(0, 8, 'I', INVERSE).unwrap();
+        }
+        let columns = [0xFF, 0xBE, 0x80, 0xBE, 0xFF];
+        for (column, expected) in columns.iter().enumerate() {
+            assert_eq!(&buffer[column * 8..column * 8 + 2], &[*expected, *expected]);
+        }
+    }
+
+    #[test]
+    fn scroll_commands_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        let mut display = recording_display(&mut buffer);
+        display.interface.clear();
+        display.start_scroll_right(0, 7, ScrollSpeed::Frames2).unwrap();
+        assert_eq!(display.interface.written(), &[0x00, 0x2E, 0x26, 0x00, 0x00, 0x07, 0x07, 0x00, 0xFF, 0x2F]);
+        display.interface.clear();
+        display.start_scroll_diag_left(2, 3, ScrollSpeed::Frames5, 1, 8, 56).unwrap();
+        assert_eq!(display.interface.written(), &[0x00, 0x2E, 0xA3, 0x08, 0x38, 0x2A, 0x00, 0x02, 0x00, 0x03, 0x01, 0x2F]);
+        display.interface.clear();
+        display.stop_scroll().unwrap();
+        assert_eq!(display.interface.written(), &[0x00, 0x2E]);
+    }
+
+    #[test]
+    fn scroll_invalid_range_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        let mut display = recording_display(&mut buffer);
+        assert!(matches!(display.start_scroll_left(3, 2, ScrollSpeed::Frames2), Err(Error::InvalidScrollRange)));
+        assert!(matches!(display.start_scroll_left(0, 8, ScrollSpeed::Frames2), Err(Error::InvalidScrollRange)));
+        assert!(matches!(display.start_scroll_diag_right(0, 7, ScrollSpeed::Frames2, 1, 16, 56), Err(Error::InvalidScrollRange)));
+        assert!(matches!(display.start_scroll_diag_right(0, 7, ScrollSpeed::Frames2, 8, 0, 8), Err(Error::InvalidScrollRange)));
+    }
+
+    #[test]
+    fn draw_rect_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = noop_display(&mut buffer);
+            display.draw_rect(0, 0, 3, 4, INVERSE).unwrap();
+        }
+        assert_eq!(&buffer[0..1], &[0b1111]);
+        assert_eq!(&buffer[8..9], &[0b1001]);
+        assert_eq!(&buffer[16..17], &[0b1111]);
+        assert_eq!(buffer.iter().filter(|byte| **byte != 0).count(), 3);
+    }
+
+    #[test]
+    fn draw_circle_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = noop_display(&mut buffer);
+            display.draw_circle(3, 3, 3, INVERSE).unwrap();
+        }
+        let columns = [0b0011100, 0b0100010, 0b1000001, 0b1000001, 0b1000001, 0b0100010, 0b0011100];
+        for (column, expected) in columns.iter().enumerate() {
+            assert_eq!(buffer[column * 8], *expected);
+        }
+        assert_eq!(buffer.iter().filter(|byte| **byte != 0).count(), 7);
+    }
+
+    #[test]
+    fn fill_circle_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = noop_display(&mut buffer);
+            display.fill_circle(3, 3, 3, INVERSE).unwrap();
+        }
+        let columns = [0b0011100, 0b0111110, 0b1111111, 0b1111111, 0b1111111, 0b0111110, 0b0011100];
+        for (column, expected) in columns.iter().enumerate() {
+            assert_eq!(buffer[column * 8], *expected);
+        }
+        assert_eq!(buffer.iter().filter(|byte| **byte != 0).count(), 7);
+    }
+
+    #[test]
+    fn round_rect_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = noop_display(&mut buffer);
+            display.draw_round_rect(0, 0, 6, 6, 2, INVERSE).unwrap();
+            display.fill_round_rect(8, 0, 6, 6, 2, INVERSE).unwrap();
+        }
+        let outline = [0b011110, 0b100001, 0b100001, 0b100001, 0b100001, 0b011110];
+        let filled = [0b011110, 0b111111, 0b111111, 0b111111, 0b111111, 0b011110];
+        for column in 0..6 {
+            assert_eq!(buffer[column * 8], outline[column]);
+            assert_eq!(buffer[(column + 8) * 8], filled[column]);
+        }
+        assert_eq!(buffer.iter().filter(|byte| **byte != 0).count(), 12);
+    }
+
+    #[test]
+    fn fill_triangle_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = noop_display(&mut buffer);
+            display.fill_triangle((0, 0), (4, 4), (0, 4), INVERSE).unwrap();
+            display.draw_triangle((8, 0), (12, 4), (8, 4), WHITE).unwrap();
+        }
+        let columns = [0b11111, 0b11110, 0b11100, 0b11000, 0b10000];
+        for (column, expected) in columns.iter().enumerate() {
+            assert_eq!(buffer[column * 8], *expected);
+        }
+        let outline = [0b11111, 0b10010, 0b10100, 0b11000, 0b10000];
+        for (column, expected) in outline.iter().enumerate() {
+            assert_eq!(buffer[(column + 8) * 8], *expected);
+        }
+    }
+
+    #[test]
+    fn clipping_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = noop_display(&mut buffer);
+            display.draw_pixel(-1, 0, WHITE).unwrap();
+            display.draw_pixel(128, 64, WHITE).unwrap();
+            display.draw_line(-10, -10, 2, 2, WHITE).unwrap();
+            display.draw_fill_rect(126, 62, 10, 10, WHITE).unwrap();
+            display.fill_circle(0, 63, 2, WHITE).unwrap();
+        }
+        assert_eq!(&buffer[0..8], &[0b0000_0001, 0, 0, 0, 0, 0, 0, 0b1110_0000]);
+        assert_eq!(&buffer[8..16], &[0b0000_0010, 0, 0, 0, 0, 0, 0, 0b1110_0000]);
+        assert_eq!(&buffer[16..24], &[0b0000_0100, 0, 0, 0, 0, 0, 0, 0b1100_0000]);
+        assert_eq!(buffer[126 * 8 + 7], 0b1100_0000);
+        assert_eq!(buffer[127 * 8 + 7], 0b1100_0000);
+        assert_eq!(buffer.iter().filter(|byte| **byte != 0).count(), 8);
+    }
+
+    #[test]
+    fn shapes_far_off_screen_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        let mut display = noop_display(&mut buffer);
+        display.draw_rect(30000, 30000, 5000, 5000, WHITE).unwrap();
+        display.draw_circle(32760, 10, 20, WHITE).unwrap();
+        display.fill_circle(i16::MAX, i16::MIN, i16::MAX, WHITE).unwrap();
+        display.draw_round_rect(i16::MIN, i16::MIN, i16::MAX, i16::MAX, 100, WHITE).unwrap();
+        display.fill_round_rect(i16::MAX, 0, i16::MAX, 64, 10, WHITE).unwrap();
+        display.fill_triangle((i16::MIN, i16::MIN), (i16::MAX, i16::MIN), (0, -1), WHITE).unwrap();
+        assert!(display.get_buffer().iter().all(|byte| *byte == 0));
+
+        // Only the right and bottom edges are on the screen
+        display.draw_rect(-30000, -30000, 30100, 30020, WHITE).unwrap();
+        assert!((0..20).all(|y| display.get_pixel(99, y)));
+        assert!((0..100).all(|x| display.get_pixel(x, 19)));
+        assert_eq!(display.get_buffer().iter().map(|byte| byte.count_ones()).sum::<u32>(), 119);
+
+        display.clear_display();
+        display.fill_triangle((-20000, 0), (20000, 10), (0, 20), WHITE).unwrap();
+        assert!(display.get_pixel(0, 10) && display.get_pixel(127, 10));
+        assert!(!display.get_pixel(0, 0) && !display.get_pixel(1, 20));
+    }
+
+    #[test]
+    fn draw_line_far_off_screen_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = noop_display(&mut buffer);
+            display.draw_line(-20000, 0, 20000, 0, WHITE).unwrap();
+            display.draw_line(i16::MIN, i16::MIN, i16::MAX, i16::MAX, WHITE).unwrap();
+        }
+        for x in 0..128 {
+            assert_eq!(buffer[x * 8] & 1, 1);
+        }
+        for i in 0..64 {
+            assert_ne!(buffer[i * 8 + i / 8] & (1 << (i % 8)), 0);
+        }
+        assert_eq!(buffer.iter().map(|byte| byte.count_ones()).sum::<u32>(), 128 + 63);
+    }
+
+    #[test]
+    fn draw_line_clipped_start_test() {
+        // The clipped line has the same pixels as the whole line drawn 20 right and 5 down
+        let mut clipped_buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        let mut whole_buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        let mut clipped = noop_display(&mut clipped_buffer);
+        let mut whole = noop_display(&mut whole_buffer);
+        for (x1, y1) in [(100, 40), (10, 58), (107, -5)] {
+            clipped.clear_display();
+            whole.clear_display();
+            clipped.draw_line(-20, -5, x1, y1, WHITE).unwrap();
+            whole.draw_line(0, 0, x1 + 20, y1 + 5, WHITE).unwrap();
+            for x in 0..108 {
+                for y in 0..59 {
+                    assert_eq!(clipped.get_pixel(x, y), whole.get_pixel(x + 20, y + 5), "({}, {}) to ({}, {})", x, y, x1, y1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn draw_char_at_edge_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = noop_display(&mut buffer);
+            // Fits exactly in the last five columns and the last page
+            display.draw_char_at(123, 56, 'I', WHITE).unwrap();
+            // Only the left half is visible
+            display.draw_char_at(126, 0, 'I', WHITE).unwrap();
+        }
+        let columns = [0x00, 0x41, 0x7F, 0x41, 0x00];
+        for (column, expected) in columns.iter().enumerate() {
+            assert_eq!(buffer[(123 + column) * 8 + 7], *expected);
+        }
+        assert_eq!(&[buffer[126 * 8], buffer[127 * 8]], &[0x00, 0x41]);
+        assert_eq!(buffer.iter().filter(|byte| **byte != 0).count(), 4);
+    }
+
+    #[test]
+    fn text_scale_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = noop_display(&mut buffer);
+            display.set_text_scale(2);
+            display.draw_char('I').unwrap();
+            assert_eq!((display.cursor_x, display.cursor_y), (12, 0));
+            display.draw_char('\n').unwrap();
+            assert_eq!((display.cursor_x, display.cursor_y), (0, 16));
+        }
+        for column in [2, 3, 6, 7] {
+            assert_eq!(&buffer[column * 8..column * 8 + 2], &[0x03, 0x30]);
+        }
+        for column in 4..6 {
+            assert_eq!(&buffer[column * 8..column * 8 + 2], &[0xFF, 0x3F]);
+        }
+        assert_eq!(buffer.iter().filter(|byte| **byte != 0).count(), 12);
+    }
+
+    #[test]
+    fn tall_font_test() {
+        static DATA: [u8; 16] = [0xFF, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x00, 0x81];
+        static FONT_8X16: Font = Font {
+            first_char: b'0',
+            last_char: b'0',
+            height: 16,
+            line_height: 16,
+            spacing: 0,
+            widths: GlyphWidths::Monospace(8),
+            data: &DATA,
+        };
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = noop_display(&mut buffer);
+            display.set_font(&FONT_8X16);
+            display.set_cursor(0, 8);
+            display.draw_char('0').unwrap();
+            assert_eq!(display.cursor_x, 8);
+            assert!(matches!(display.draw_char('1'), Err(Error::InvalidChar('1'))));
+        }
+        assert_eq!(buffer[1], 0xFF);
+        assert_eq!(buffer[7 * 8 + 2], 0x81);
+        assert_eq!(buffer.iter().filter(|byte| **byte != 0).count(), 2);
+    }
+
+    #[test]
+    fn cp437_char_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = noop_display(&mut buffer);
+            display.draw_char('°').unwrap();
+            display.draw_char('€').unwrap();
+            display.set_replacement_char(Some(0xFE));
+            display.draw_char('€').unwrap();
+            display.set_replacement_char(None);
+            assert!(matches!(display.draw_char('€'), Err(Error::InvalidChar('€'))));
+            assert_eq!(display.cursor_x, 18);
+        }
+        assert_eq!(&[buffer[0], buffer[8], buffer[16]], &[0x06, 0x0F, 0x09]);
+        assert_eq!(&[buffer[48], buffer[56], buffer[64]], &[0x02, 0x01, 0x59]);
+        assert_eq!(&[buffer[96], buffer[104], buffer[112], buffer[120]], &[0x00, 0x3C, 0x3C, 0x3C]);
+    }
+
+    #[test]
+    fn measure_text_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        let mut display = noop_display(&mut buffer);
+        assert_eq!(display.measure_text(""), (0, 0));
+        assert_eq!(display.measure_text("Hi"), (11, 8));
+        assert_eq!(display.measure_text("ab\nabc"), (17, 16));
+        display.set_text_scale(2);
+        assert_eq!(display.measure_text("Hi"), (22, 16));
+        display.set_text_scale(255);
+        assert_eq!(display.measure_text("a\nb"), (1275, 4080));
+        assert_eq!(display.measure_text(&"\n".repeat(20)), (0, i16::MAX));
+        let drawn = display.draw_text_box("a\nb\nc", Rect::new(0, 0, 128, 64), Align::Left, Overflow::Ellipsis, WHITE).unwrap();
+        assert_eq!(drawn, 0);
+        display.set_text_scale(2);
+        display.set_replacement_char(None);
+        assert!(matches!(display.draw_string("a€"), Err(Error::InvalidChar('€'))));
+    }
+
+    #[test]
+    fn text_box_wrap_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = noop_display(&mut buffer);
+            let drawn = display.draw_text_box("one two three", Rect::new(0, 0, 30, 24), Align::Left, Overflow::Truncate, WHITE).unwrap();
+            assert_eq!(drawn, 13);
+            assert_eq!(display.clip_rect(), Rect::new(0, 0, 128, 64));
+        }
+        let used_columns = |page: usize| (0..128).filter(|column| buffer[column * 8 + page] != 0).max();
+        assert_eq!(used_columns(0), Some(16));
+        assert_eq!(used_columns(1), Some(16));
+        assert_eq!(used_columns(2), Some(28));
+        assert_eq!(used_columns(3), None);
+    }
+
+    #[test]
+    fn text_box_align_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = noop_display(&mut buffer);
+            display.draw_text_box("Hi", Rect::new(0, 0, 21, 8), Align::Center, Overflow::Truncate, WHITE).unwrap();
+            display.draw_text_box("Hi", Rect::new(0, 8, 21, 8), Align::Right, Overflow::Truncate, WHITE).unwrap();
+        }
+        // The first column of 'H' is solid
+        assert_eq!((buffer[4 * 8], buffer[5 * 8]), (0x00, 0x7F));
+        assert_eq!((buffer[9 * 8 + 1], buffer[10 * 8 + 1]), (0x00, 0x7F));
+    }
+
+    #[test]
+    fn text_box_overflow_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = noop_display(&mut buffer);
+            let drawn = display.draw_text_box("one two three", Rect::new(0, 0, 30, 16), Align::Left, Overflow::Truncate, WHITE).unwrap();
+            assert_eq!(drawn, 8);
+            display.fill_screen(BLACK);
+            let drawn = display.draw_text_box("one two three", Rect::new(0, 0, 30, 16), Align::Left, Overflow::Ellipsis, WHITE).unwrap();
+            assert_eq!(drawn, 6);
+            assert_eq!(display.draw_text_box("one", Rect::new(0, 0, 30, 7), Align::Left, Overflow::Truncate, WHITE).unwrap(), 0);
+        }
+        // "tw..." on the second line, the dots start at column 12
+        assert_eq!(&[buffer[13 * 8 + 1], buffer[14 * 8 + 1], buffer[15 * 8 + 1]], &[0x00, 0x60, 0x60]);
+        assert_eq!(&[buffer[26 * 8 + 1], buffer[27 * 8 + 1]], &[0x60, 0x60]);
+        assert_eq!(buffer.iter().skip(28 * 8).filter(|byte| **byte != 0).count(), 0);
+    }
+
+    #[test]
+    fn write_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = noop_display(&mut buffer);
+            uWrite::write_str(&mut display, "T=").unwrap();
+            write!(display, "{}", 21).unwrap();
+            assert_eq!((display.cursor_x, display.cursor_y), (24, 0));
+        }
+        // The first column of 'T'
+        assert_eq!(buffer[0], 0x03);
+    }
+
+    #[test]
+    fn text_scroll_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = noop_display(&mut buffer);
+            display.draw_pixel(0, 8, WHITE).unwrap();
+            display.draw_pixel(1, 63, WHITE).unwrap();
+            display.set_text_scroll(true);
+            display.set_cursor(0, 56);
+            uWrite::write_str(&mut display, "\n_").unwrap();
+            assert_eq!((display.cursor_x, display.cursor_y), (6, 56));
+        }
+        assert_eq!(buffer[0], 0x01);
+        assert_eq!(buffer[8 + 6], 0x80);
+        assert_eq!(buffer[7], 0x40);
+    }
+
+    #[test]
+    fn scroll_up_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = noop_display(&mut buffer);
+            display.draw_pixel(0, 10, WHITE).unwrap();
+            display.draw_pixel(1, 63, WHITE).unwrap();
+            display.draw_pixel(2, 1, WHITE).unwrap();
+            display.scroll_up(3);
+        }
+        assert_eq!(buffer[0], 0x80);
+        assert_eq!(buffer[8 + 7], 0x10);
+        assert_eq!(buffer.iter().filter(|byte| **byte != 0).count(), 2);
+    }
+
+    #[test]
+    fn draw_bitmap_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = noop_display(&mut buffer);
+            let row_major = Bitmap::new(8, 2, BitmapFormat::RowMajor, &[0b1000_0001, 0b0100_0000]);
+            display.draw_bitmap(1, 1, &row_major, None, WHITE).unwrap();
+            let xbm = Bitmap::new(3, 1, BitmapFormat::Xbm, &[0b0000_0100]);
+            display.draw_bitmap(-2, 0, &xbm, None, WHITE).unwrap();
+            // Not page aligned, so drawn pixel by pixel
+            let page_major = Bitmap::new(1, 8, BitmapFormat::PageMajor, &[0b1000_0001]);
+            display.draw_bitmap(20, 3, &page_major, None, WHITE).unwrap();
+            display.draw_bitmap(20, 3, &page_major, None, INVERSE).unwrap();
+            display.draw_bitmap(21, 3, &page_major, None, WHITE).unwrap();
+        }
+        assert_eq!(buffer[0], 0b01);
+        assert_eq!(buffer[8], 0b10);
+        assert_eq!(buffer[2 * 8], 0b100);
+        assert_eq!(buffer[8 * 8], 0b10);
+        assert_eq!(&buffer[21 * 8..21 * 8 + 2], &[0b1000, 0b100]);
+        assert_eq!(buffer.iter().filter(|byte| **byte != 0).count(), 6);
+    }
+
+    #[test]
+    fn draw_bitmap_page_aligned_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = recording_display(&mut buffer);
+            display.flush().unwrap();
+            let page_major = Bitmap::new(2, 12, BitmapFormat::PageMajor, &[0xF0, 0x0F, 0xFF, 0xFF]);
+            display.draw_bitmap(3, 8, &page_major, None, WHITE).unwrap();
+            assert_eq!(display.dirty, Some(DirtyArea { first_column: 3, last_column: 4, first_page: 1, last_page: 2 }));
+            display.draw_bitmap(3, 8, &page_major, None, INVERSE).unwrap();
+            display.fill_screen(WHITE);
+            let mask = Bitmap::new(2, 8, BitmapFormat::PageMajor, &[0xFF, 0x00]);
+            display.draw_bitmap(3, 16, &page_major, Some(&mask), BLACK).unwrap();
+        }
+        assert_eq!(&buffer[3 * 8 + 1..3 * 8 + 4], &[0xFF, 0x0F, 0xFF]);
+        assert_eq!(&buffer[4 * 8 + 1..4 * 8 + 4], &[0xFF, 0xFF, 0xFF]);
+        assert_eq!(buffer.iter().filter(|byte| **byte != 0xFF).count(), 1);
+    }
+
+    #[test]
+    fn buffer_access_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        let mut display = noop_display(&mut buffer);
+        display.draw_pixel(3, 10, WHITE).unwrap();
+        assert!(display.get_pixel(3, 10));
+        assert!(!display.get_pixel(3, 9));
+        assert!(!display.get_pixel(-1, 0) && !display.get_pixel(128, 0) && !display.get_pixel(0, 64));
+        assert_eq!(display.get_buffer()[3 * 8 + 1], 0b100);
+
+        display.flush().unwrap();
+        display.get_buffer_mut()[0] = 0x01;
+        assert!(display.is_dirty());
+        assert!(display.get_pixel(0, 0));
+
+        let (interface, buffer) = display.release();
+        let NoopI2c = interface.release();
+        assert_eq!(buffer[0], 0x01);
+    }
+
+    #[test]
+    fn clip_rect_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = noop_display(&mut buffer);
+            display.set_clip_rect(Some(Rect::new(1, 2, 2, 4)));
+            display.fill_screen(BLACK);
+            display.draw_fill_rect(0, 0, 10, 10, WHITE).unwrap();
+            display.draw_line(0, 0, 9, 9, INVERSE).unwrap();
+            display.set_clip_rect(None);
+            assert_eq!(display.clip_rect(), Rect::new(0, 0, 128, 64));
+        }
+        assert_eq!(&[buffer[0], buffer[8], buffer[16], buffer[24]], &[0x00, 0b0011_1100, 0b0011_1000, 0x00]);
+        assert_eq!(buffer.iter().filter(|byte| **byte != 0).count(), 2);
+    }
+
+    #[test]
+    fn strict_mode_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        let mut display = noop_display(&mut buffer);
+        display.set_strict(true);
+        assert!(display.draw_char_at(123, 56, 'I', WHITE).is_ok());
+        assert!(matches!(display.draw_pixel(128, 0, WHITE), Err(Error::OutsideScreenAccess { x: 128, y: 0 })));
+        assert!(matches!(display.draw_fast_h_line(120, 0, 10, WHITE), Err(Error::OutsideScreenAccess { x: 129, y: 0 })));
+        assert!(matches!(display.draw_fill_rect(-1, 0, 2, 2, WHITE), Err(Error::OutsideScreenAccess { x: -1, y: 0 })));
+        display.set_clip_rect(Some(Rect::new(0, 0, 10, 10)));
+        assert!(matches!(display.draw_pixel(10, 0, WHITE), Err(Error::OutsideScreenAccess { x: 10, y: 0 })));
+    }
+
+    #[test]
+    fn flush_dirty_area_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        let mut display = recording_display(&mut buffer);
+        display.display().unwrap();
+        assert!(!display.is_dirty());
+        display.interface.clear();
+        display.flush().unwrap();
+        assert_eq!(display.interface.written(), &[]);
+
+        display.draw_pixel(3, 9, WHITE).unwrap();
+        display.draw_fast_h_line(1, 20, 3, WHITE).unwrap();
+        assert!(display.is_dirty());
+        display.flush().unwrap();
+        // Columns 1 to 3, pages 1 to 2
+        assert_eq!(display.interface.written(), &[
+            0x00, 0x21, 0x01, 0x03, 0x22, 0x01, 0x02,
+            0x40, 0x00, 0x10, 0x00, 0x10, 0x02, 0x10,
+        ]);
+        assert!(!display.is_dirty());
+    }
+
+    #[test]
+    fn flush_chunks_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        let mut display = recording_display(&mut buffer);
+        display.display().unwrap();
+        display.draw_fill_rect(0, 0, 5, 64, WHITE).unwrap();
+        display.interface.clear();
+        display.flush().unwrap();
+        let written = display.interface.written();
+        assert_eq!(&written[..7], &[0x00, 0x21, 0x00, 0x04, 0x22, 0x00, 0x07]);
+        // 40 bytes of data, sent as one full chunk of 32 and one of 8
+        assert_eq!(written.len(), 7 + 1 + 32 + 1 + 8);
+        assert_eq!(written[7], 0x40);
+        assert_eq!(written[7 + 33], 0x40);
+        assert!(written[8..40].iter().chain(&written[41..]).all(|byte| *byte == 0xFF));
+    }
+
+    #[test]
+    fn shadow_exact_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        let mut shadow = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        let mut display = recording_display(&mut buffer);
+        display.set_shadow(Some(&mut shadow)).unwrap();
+        display.display().unwrap();
+        display.interface.clear();
+        display.display().unwrap();
+        assert_eq!(display.interface.written(), &[]);
+
+        // Only the changed columns are sent, column 4 is skipped
+        display.draw_pixel(3, 9, WHITE).unwrap();
+        display.draw_pixel(5, 9, WHITE).unwrap();
+        display.flush().unwrap();
+        assert_eq!(display.interface.written(), &[
+            0x00, 0x21, 0x03, 0x03, 0x22, 0x01, 0x01, 0x40, 0x02,
+            0x00, 0x21, 0x05, 0x05, 0x22, 0x01, 0x01, 0x40, 0x02,
+        ]);
+
+        // Redrawn the same, so dirty but unchanged
+        display.interface.clear();
+        display.clear_display();
+        display.draw_pixel(3, 9, WHITE).unwrap();
+        display.draw_pixel(5, 9, WHITE).unwrap();
+        assert!(display.is_dirty());
+        display.display().unwrap();
+        assert_eq!(display.interface.written(), &[]);
+
+        display.resend_all();
+        display.flush().unwrap();
+        assert_eq!(&display.interface.written()[..7], &[0x00, 0x21, 0x00, 0x7F, 0x22, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn shadow_uneven_segments_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        // 3 segments per page, of 43, 43 and 42 columns
+        let mut shadow = [0x00; 24];
+        let mut display = recording_display(&mut buffer);
+        display.set_shadow(Some(&mut shadow)).unwrap();
+        display.interface.clear();
+        display.display().unwrap();
+        assert_eq!(&display.interface.written()[..7], &[0x00, 0x21, 0x00, 0x7F, 0x22, 0x00, 0x00]);
+
+        display.interface.clear();
+        display.draw_pixel(127, 0, WHITE).unwrap();
+        display.draw_pixel(50, 9, WHITE).unwrap();
+        display.flush().unwrap();
+        // Data goes out in chunks of 32 bytes
+        let written = display.interface.written();
+        assert_eq!(&written[..8], &[0x00, 0x21, 86, 127, 0x22, 0x00, 0x00, 0x40]);
+        assert_eq!(&written[40..51], &[0x40, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01]);
+        assert_eq!(&written[51..59], &[0x00, 0x21, 43, 85, 0x22, 0x01, 0x01, 0x40]);
+        assert_eq!(written.len(), 59 + 32 + 1 + 11);
+
+        // Every shadow size stays inside the screen
+        for length in 8..=DisplaySize128x64::BUFFER_SIZE {
+            let mut shadow = vec![0x00; length];
+            let mut display = recording_display(&mut buffer);
+            display.set_shadow(Some(&mut shadow)).unwrap();
+            display.display().unwrap();
+            display.draw_pixel(127, 63, INVERSE).unwrap();
+            display.flush().unwrap();
+        }
+    }
+
+    #[test]
+    fn shadow_checksum_test() {
+        let mut buffer = [0x00; DisplaySize96x16::BUFFER_SIZE];
+        let mut shadow = [0x00; 25];
+        let mut too_small = [0x00; 1];
+        let mut display = DisplayDriver::new(RecordingInterface::new(), &mut buffer, DisplaySize96x16, Config::default()).unwrap();
+        assert!(matches!(display.set_shadow(Some(&mut too_small)), Err(Error::ShadowTooSmall)));
+        // 12 segments of 8 columns per page
+        display.set_shadow(Some(&mut shadow)).unwrap();
+        display.interface.clear();
+        display.display().unwrap();
+        assert_eq!(&display.interface.written()[..8], &[0x00, 0x21, 0x00, 0x5F, 0x22, 0x00, 0x00, 0x40]);
+
+        display.interface.clear();
+        display.draw_pixel(10, 3, WHITE).unwrap();
+        display.draw_pixel(17, 3, WHITE).unwrap();
+        display.flush().unwrap();
+        assert_eq!(display.interface.written(), &[
+            0x00, 0x21, 0x08, 0x17, 0x22, 0x00, 0x00,
+            0x40, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ]);
+
+        // A failed or bypassing write makes the next update send everything
+        display.display_num(0).unwrap();
+        display.interface.clear();
+        display.flush().unwrap();
+        assert_eq!(&display.interface.written()[..7], &[0x00, 0x21, 0x00, 0x5F, 0x22, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn shadow_diagonal_line_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        let mut shadow = [0x00; 128];
+        let mut display = recording_display(&mut buffer);
+        // 16 segments of 8 columns per page
+        display.set_shadow(Some(&mut shadow)).unwrap();
+        display.display().unwrap();
+        display.interface.clear();
+        display.draw_line(64, 8, 71, 15, WHITE).unwrap();
+        display.flush().unwrap();
+        assert_eq!(display.interface.written(), &[
+            0x00, 0x21, 0x40, 0x47, 0x22, 0x01, 0x01,
+            0x40, 0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80,
+        ]);
+    }
+
+    #[test]
+    fn rotation_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = recording_display(&mut buffer);
+            display.set_rotation(Rotation::Rotate90);
+            assert_eq!((display.width(), display.height()), (64, 128));
+            assert_eq!(display.clip_rect(), Rect::new(0, 0, 64, 128));
+            display.draw_pixel(0, 0, WHITE).unwrap();
+            display.draw_fast_h_line(0, 1, 3, WHITE).unwrap();
+            display.draw_fast_v_line(2, 10, 2, WHITE).unwrap();
+            display.draw_pixel(63, 127, WHITE).unwrap();
+            display.draw_pixel(64, 0, WHITE).unwrap();
+            assert!(display.get_pixel(1, 1) && display.get_pixel(63, 127));
+            assert!(!display.get_pixel(3, 1) && !display.get_pixel(64, 0));
+        }
+        // The top left corner is at the top right of the panel, rows run right to left
+        assert_eq!(buffer[127 * 8], 0b001);
+        assert_eq!(buffer[126 * 8], 0b111);
+        assert_eq!((buffer[116 * 8], buffer[117 * 8]), (0b100, 0b100));
+        assert_eq!(buffer[7], 0x80);
+        assert_eq!(buffer.iter().filter(|byte| **byte != 0).count(), 5);
+
+        buffer.fill(0x00);
+        {
+            let mut display = recording_display(&mut buffer);
+            display.set_rotation(Rotation::Rotate270);
+            display.draw_pixel(0, 0, WHITE).unwrap();
+            display.set_rotation(Rotation::Rotate180);
+            assert_eq!((display.width(), display.height()), (128, 64));
+            display.draw_fill_rect(0, 0, 2, 1, WHITE).unwrap();
+        }
+        assert_eq!(buffer[7], 0x80);
+        assert_eq!((buffer[126 * 8 + 7], buffer[127 * 8 + 7]), (0x80, 0x80));
+    }
+
+    #[test]
+    fn rotated_text_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        let config = Config { rotation: Rotation::Rotate90, ..Config::default() };
+        let mut display = DisplayDriver::new(RecordingInterface::new(), &mut buffer, DisplaySize128x64, config).unwrap();
+        // Ten 6 pixel wide characters fit on a 64 pixel wide line
+        display.draw_string("0123456789").unwrap();
+        assert_eq!((display.cursor_x, display.cursor_y), (60, 0));
+        display.draw_char('A').unwrap();
+        assert_eq!((display.cursor_x, display.cursor_y), (6, 8));
+
+        display.clear_display();
+        display.draw_pixel(5, 10, WHITE).unwrap();
+        display.scroll_up(4);
+        assert!(display.get_pixel(5, 6) && !display.get_pixel(5, 10));
+        assert_eq!(display.get_buffer().iter().filter(|byte| **byte != 0).count(), 1);
+    }
+
+    #[test]
+    fn golden_rotated_test() {
+        let mut buffer = [0x00; DisplaySize96x16::BUFFER_SIZE];
+        {
+            let config = Config { rotation: Rotation::Rotate270, ..Config::default() };
+            let mut display = DisplayDriver::new(I2cInterface::new(NoopI2c, None), &mut buffer, DisplaySize96x16, config).unwrap();
+            display.draw_rect(0, 0, 16, 96, WHITE).unwrap();
+            display.set_cursor(2, 2);
+            display.draw_string("Hi").unwrap();
+            display.fill_circle(8, 80, 5, WHITE).unwrap();
+        }
+        assert_golden("rotated.txt", &render_ascii::<DisplaySize96x16>(&buffer));
+    }
+
+    #[test]
+    fn display_size_128x32_test() {
+        let mut buffer = [0x00; DisplaySize128x32::BUFFER_SIZE];
+        {
+            let mut display = DisplayDriver::new(RecordingInterface::new(), &mut buffer, DisplaySize128x32, Config::default()).unwrap();
+            // Multiplex ratio and COM pins configuration
+            assert_eq!(&display.interface.written()[..6], &[0x00, 0xAE, 0xD5, 0x80, 0xA8, 0x1F]);
+            assert!(display.interface.written().windows(3).any(|command| command == [0x00, 0xDA, 0x02]));
+            assert_eq!((display.width(), display.height()), (128, 32));
+
+            display.draw_fill_rect(120, 28, 20, 20, WHITE).unwrap();
+            display.interface.clear();
+            display.display().unwrap();
+            assert_eq!(&display.interface.written()[..7], &[0x00, 0x21, 0x00, 0x7F, 0x22, 0x00, 0x03]);
+        }
+        for column in 120..128 {
+            assert_eq!(&buffer[column * 4..column * 4 + 4], &[0x00, 0x00, 0x00, 0xF0]);
+        }
+        assert_eq!(buffer.iter().filter(|byte| **byte != 0).count(), 8);
+    }
+
+    #[test]
+    fn display_size_96x16_test() {
+        let mut buffer = [0x00; DisplaySize96x16::BUFFER_SIZE];
+        let mut display = DisplayDriver::new(RecordingInterface::new(), &mut buffer, DisplaySize96x16, Config::default()).unwrap();
+        assert_eq!(&display.interface.written()[..6], &[0x00, 0xAE, 0xD5, 0x80, 0xA8, 0x0F]);
+        assert_eq!(display.clip_rect(), Rect::new(0, 0, 96, 16));
+        display.set_strict(true);
+        assert!(display.draw_pixel(95, 15, WHITE).is_ok());
+        assert!(display.draw_pixel(96, 0, WHITE).is_err());
+        assert!(display.start_scroll_left(0, 2, ScrollSpeed::Frames2).is_err());
+    }
+
+    #[test]
+    fn display_size_128x64_com_pins_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        let display = recording_display(&mut buffer);
+        assert!(display.interface.written().windows(3).any(|command| command == [0x00, 0xDA, 0x12]));
+    }
+
+    #[test]
+    fn config_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        let config = Config {
+            vcc_state: EXTERNALVCC,
+            contrast: 0x30,
+            display_offset: 4,
+            start_line: 2,
+            clock_div: 0xF0,
+            ..Config::default()
+        }.rotated_180();
+        let mut display = DisplayDriver::new(RecordingInterface::new(), &mut buffer, DisplaySize128x64, config).unwrap();
+        assert_eq!(display.interface.written(), &[
+            0x00, 0xAE, 0xD5, 0xF0, 0xA8, 0x3F,
+            0x00, 0xD3, 0x04, 0x42, 0x8D,
+            0x00, 0x10,
+            0x00, 0x20, 0x01, 0xA0, 0xC0,
+            0x00, 0xDA, 0x12,
+            0x00, 0x81, 0x30,
+            0x00, 0xD9, 0x22,
+            0x00, 0xDB, 0x40, 0xA4, 0xA6, 0x2E, 0xAF,
+        ]);
+        display.interface.clear();
+        display.dim(true).unwrap();
+        display.dim(false).unwrap();
+        assert_eq!(display.interface.written(), &[0x00, 0x81, 0x00, 0x00, 0x81, 0x30]);
+    }
+
+    #[test]
+    fn brightness_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        let config = Config { precharge: Some(0x1F), vcomh: 0x20, ..Config::default() };
+        let mut display = DisplayDriver::new(RecordingInterface::new(), &mut buffer, DisplaySize128x64, config).unwrap();
+        assert!(display.interface.written().windows(3).any(|command| command == [0x00, 0xD9, 0x1F]));
+        assert!(display.interface.written().windows(3).any(|command| command == [0x00, 0xDB, 0x20]));
+
+        display.interface.clear();
+        display.set_contrast(0x20).unwrap();
+        display.set_precharge(0x22).unwrap();
+        display.set_vcomh(0x30).unwrap();
+        display.dim(true).unwrap();
+        display.dim(false).unwrap();
+        assert_eq!(display.interface.written(), &[
+            0x00, 0x81, 0x20, 0x00, 0xD9, 0x22, 0x00, 0xDB, 0x30,
+            0x00, 0x81, 0x00, 0x00, 0x81, 0x20,
+        ]);
+    }
+
+    #[test]
+    fn fade_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        let mut display = recording_display(&mut buffer);
+        assert!(!display.tick(10).unwrap());
+        display.fade_to(0x0F, 100);
+        assert!(display.is_fading());
+        display.interface.clear();
+        assert!(display.tick(25).unwrap());
+        assert!(display.tick(25).unwrap());
+        // Unchanged contrast isn't sent again
+        assert!(display.tick(0).unwrap());
+        assert!(!display.tick(60).unwrap());
+        assert!(!display.is_fading());
+        assert_eq!(display.interface.written(), &[0x00, 0x81, 0x6F, 0x00, 0x81, 0x4F, 0x00, 0x81, 0x0F]);
+
+        display.fade_to(0xFF, 0);
+        display.interface.clear();
+        assert!(!display.tick(0).unwrap());
+        assert_eq!(display.interface.written(), &[0x00, 0x81, 0xFF]);
+    }
+
+    /// Records the levels it is set to.
+    struct RecordingPin {
+        levels: [bool; 4],
+        length: usize,
+    }
+
+    impl digital::ErrorType for RecordingPin {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for RecordingPin {
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            self.levels[self.length] = false;
+            self.length += 1;
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            self.levels[self.length] = true;
+            self.length += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn reset_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        let mut reset = RecordingPin { levels: [false; 4], length: 0 };
+        let waited = Cell::new(0);
+        let display = DisplayDriver::new_with_reset(RecordingInterface::new(), &mut reset, |ms| waited.set(waited.get() + ms), &mut buffer, DisplaySize128x64, Config::default()).unwrap();
+        assert_eq!(&display.interface.written()[..2], &[0x00, 0xAE]);
+        assert_eq!(&reset.levels[..reset.length], &[true, false, true]);
+        assert_eq!(waited.get(), 11);
+    }
+
+    #[test]
+    fn sleep_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        let mut display = recording_display(&mut buffer);
+        display.draw_pixel(0, 0, WHITE).unwrap();
+        display.interface.clear();
+        display.sleep().unwrap();
+        assert_eq!(display.interface.written(), &[0x00, 0xAE, 0x8D, 0x10]);
+        display.interface.clear();
+        display.wake().unwrap();
+        assert_eq!(display.interface.written(), &[0x00, 0x8D, 0x14, 0xAF]);
+        assert_eq!(display.buffer[0], 0x01);
+
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        let config = Config { vcc_state: EXTERNALVCC, ..Config::default() };
+        let mut display = DisplayDriver::new(RecordingInterface::new(), &mut buffer, DisplaySize128x64, config).unwrap();
+        display.interface.clear();
+        display.wake().unwrap();
+        assert_eq!(display.interface.written(), &[0x00, 0x8D, 0x10, 0xAF]);
+    }
+
+    #[test]
+    fn golden_shapes_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = noop_display(&mut buffer);
+            display.draw_line(0, 0, 127, 63, WHITE).unwrap();
+            display.draw_rect(2, 2, 30, 20, WHITE).unwrap();
+            display.draw_circle(50, 16, 12, WHITE).unwrap();
+            display.fill_circle(90, 16, 10, WHITE).unwrap();
+            display.draw_round_rect(4, 34, 40, 26, 6, WHITE).unwrap();
+            display.fill_triangle((60, 60), (80, 36), (100, 60), INVERSE).unwrap();
+            display.draw_fill_rect(104, 40, 20, 20, WHITE).unwrap();
+            display.draw_line(104, 40, 123, 59, BLACK).unwrap();
+        }
+        assert_golden("shapes.txt", &render_ascii::<DisplaySize128x64>(&buffer));
+    }
+
+    #[test]
+    fn golden_text_test() {
+        let mut buffer = [0x00; DisplaySize128x32::BUFFER_SIZE];
+        {
+            let mut display = DisplayDriver::new(I2cInterface::new(NoopI2c, None), &mut buffer, DisplaySize128x32, Config::default()).unwrap();
+            display.set_text_scale(2);
+            display.draw_string("12:34").unwrap();
+            display.set_text_scale(1);
+            let text = "Centered text wraps at spaces and ends with an ellipsis";
+            display.draw_text_box(text, Rect::new(0, 16, 128, 16), Align::Center, Overflow::Ellipsis, WHITE).unwrap();
+        }
+        assert_golden("text.pbm", &render_pbm::<DisplaySize128x32>(&buffer));
+    }
+
+    #[test]
+    fn golden_flush_test() {
+        let mut buffer = [0x00; DisplaySize96x16::BUFFER_SIZE];
+        let mut display = DisplayDriver::new(I2cInterface::new(RecordingI2c::new(), None), &mut buffer, DisplaySize96x16, Config::default()).unwrap();
+        display.flush().unwrap();
+        display.draw_fast_h_line(10, 9, 40, WHITE).unwrap();
+        display.flush().unwrap();
+        display.flush().unwrap();
+        let (interface, _) = display.release();
+        assert_golden("flush.txt", &interface.release().dump());
     }
 }
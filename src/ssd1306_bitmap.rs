@@ -0,0 +1,76 @@
+/// Layout of the bytes of a `Bitmap`. Set bits are drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BitmapFormat {
+    /// Row after row, each row padded to whole bytes with the most significant bit leftmost,
+    /// as used by Adafruit GFX `drawBitmap`
+    RowMajor,
+    /// Like `RowMajor` but with the least significant bit leftmost, as in XBM files
+    Xbm,
+    /// The SSD1306's own layout: a row of bytes per page, one byte per column with the least
+    /// significant bit on top
+    PageMajor,
+}
+
+/// A monochrome image or mask, `width` by `height` pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bitmap<'a> {
+    pub width: i16,
+    pub height: i16,
+    pub format: BitmapFormat,
+    pub data: &'a [u8],
+}
+
+impl<'a> Bitmap<'a> {
+    pub const fn new(width: i16, height: i16, format: BitmapFormat, data: &'a [u8]) -> Self {
+        Self { width, height, format, data }
+    }
+
+    /// Whether the pixel at `x`, `y` is set. Pixels outside the bitmap or its data are not.
+    pub fn is_set(&self, x: i16, y: i16) -> bool {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return false;
+        }
+        let (x, y) = (x as usize, y as usize);
+        let row_bytes = (self.width as usize).div_ceil(8);
+        let (index, bit) = match self.format {
+            BitmapFormat::RowMajor => (y * row_bytes + x / 8, 7 - x % 8),
+            BitmapFormat::Xbm => (y * row_bytes + x / 8, x % 8),
+            BitmapFormat::PageMajor => ((y / 8) * self.width as usize + x, y % 8),
+        };
+        self.data.get(index).is_some_and(|byte| byte & (1 << bit) != 0)
+    }
+
+    /// The byte for `column` and `page` of a `PageMajor` bitmap, 0 if it is missing.
+    pub fn page_byte(&self, column: i16, page: i16) -> u8 {
+        let index = page as usize * self.width as usize + column as usize;
+        self.data.get(index).copied().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ssd1306_bitmap::{Bitmap, BitmapFormat};
+
+    #[test]
+    fn is_set_test() {
+        let data = [0b1000_0001, 0b0100_0000, 0b0000_0010, 0b0000_0000];
+        let row_major = Bitmap::new(9, 2, BitmapFormat::RowMajor, &data);
+        assert!(row_major.is_set(0, 0) && row_major.is_set(7, 0));
+        assert!(row_major.is_set(6, 1));
+        assert!(!row_major.is_set(1, 1) && !row_major.is_set(8, 0));
+        assert!(!row_major.is_set(9, 0) && !row_major.is_set(0, 2) && !row_major.is_set(-1, 0));
+
+        let xbm = Bitmap::new(9, 2, BitmapFormat::Xbm, &data);
+        assert!(xbm.is_set(0, 0) && xbm.is_set(7, 0));
+        assert!(xbm.is_set(1, 1));
+        assert!(!xbm.is_set(6, 0) && !xbm.is_set(6, 1));
+
+        let page_major = Bitmap::new(2, 16, BitmapFormat::PageMajor, &data);
+        assert!(page_major.is_set(0, 0) && page_major.is_set(0, 7));
+        assert!(page_major.is_set(1, 6));
+        assert!(page_major.is_set(0, 9));
+        assert!(!page_major.is_set(1, 8));
+        assert_eq!(page_major.page_byte(1, 0), 0b0100_0000);
+        assert_eq!(page_major.page_byte(1, 2), 0);
+    }
+}
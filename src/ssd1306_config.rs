@@ -0,0 +1,71 @@
+use crate::ssd1306_registers::SWITCHCAPVCC;
+
+/// Rotation of the drawing coordinates, done in software when drawing into the buffer. With
+/// `Rotate90` and `Rotate270` width and height are swapped, so a 128x64 panel becomes a 64x128
+/// canvas.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rotation {
+    Rotate0,
+    /// Turned clockwise, the top of the canvas is at the right edge of the panel
+    Rotate90,
+    /// `Config::rotated_180` does the same in hardware, without the cost of rotating
+    Rotate180,
+    /// Turned counterclockwise, the top of the canvas is at the left edge of the panel
+    Rotate270,
+}
+
+/// Settings sent to the display by `DisplayDriver::new`. The default matches a typical module
+/// powered through its internal charge pump and mounted the right way up.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Config {
+    /// `SWITCHCAPVCC` to generate the panel voltage with the charge pump, or `EXTERNALVCC`
+    /// for boards that supply it externally.
+    pub vcc_state: u8,
+    pub contrast: u8,
+    /// Raw SETPRECHARGE value, the low nibble is phase 1 and the high nibble phase 2 in display
+    /// clocks. `None` picks 0x22 for `EXTERNALVCC` and 0xF1 for the charge pump.
+    pub precharge: Option<u8>,
+    /// Raw SETVCOMDETECT value for the COM deselect voltage level. 0x00, 0x20 and 0x30 are
+    /// 0.65, 0.77 and 0.83 times VCC, higher values make the image brighter.
+    pub vcomh: u8,
+    /// Mirrors the image left to right, using the segment remap.
+    pub flip_horizontal: bool,
+    /// Mirrors the image top to bottom, using the COM scan direction.
+    pub flip_vertical: bool,
+    /// Vertical shift of the image in rows, 0 to 63.
+    pub display_offset: u8,
+    /// The display RAM row shown at the top of the panel, 0 to 63.
+    pub start_line: u8,
+    /// Raw SETDISPLAYCLOCKDIV value. The high nibble is the oscillator frequency and the low
+    /// nibble the divide ratio minus one.
+    pub clock_div: u8,
+    pub rotation: Rotation,
+}
+
+impl Config {
+    /// Flips the image both ways, for modules mounted upside down.
+    pub fn rotated_180(self) -> Self {
+        Self {
+            flip_horizontal: !self.flip_horizontal,
+            flip_vertical: !self.flip_vertical,
+            ..self
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            vcc_state: SWITCHCAPVCC,
+            contrast: 0x8F,
+            precharge: None,
+            vcomh: 0x40,
+            flip_horizontal: false,
+            flip_vertical: false,
+            display_offset: 0,
+            start_line: 0,
+            clock_div: 0x80,
+            rotation: Rotation::Rotate0,
+        }
+    }
+}
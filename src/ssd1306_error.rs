@@ -1,26 +1,36 @@
+#[cfg(feature = "string-errors")]
 use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+use embedded_hal::spi;
 use ufmt::{Formatter, uWrite};
 
-pub enum Error<I2CError> {
+/// Display driver errors. `E` is the I2C error type, or `Infallible` for SPI displays.
+#[derive(Debug)]
+pub enum Error<E> {
     WrongChipId(u8),
-    I2cError(I2CError),
+    I2cError(E),
+    SpiError(spi::ErrorKind),
+    /// Setting the D/C or reset pin failed
+    PinError,
     OutsideScreenAccess {
         x: i16,
         y: i16,
     },
     InvalidChar(char),
+    InvalidScrollRange,
+    /// The shadow buffer has less than one byte per page
+    ShadowTooSmall,
 }
 
-impl<I2CError> From<I2CError> for Error<I2CError>
-    where I2CError: embedded_hal::i2c::Error {
-    fn from(value: I2CError) -> Self {
+impl<E> From<E> for Error<E>
+    where E: embedded_hal::i2c::Error {
+    fn from(value: E) -> Self {
         Self::I2cError(value)
     }
 }
 
 #[cfg(feature = "string-errors")]
-impl<I2CError> ufmt::uDisplay for Error<I2CError>
-    where I2CError: embedded_hal::i2c::Error {
+impl<E> ufmt::uDisplay for Error<E>
+    where E: embedded_hal::i2c::Error {
     fn fmt<W>(&self, fmt: &mut Formatter<'_, W>) -> Result<(), <W as uWrite>::Error> where W: uWrite + ?Sized {
         match self {
             Error::WrongChipId(id) => {
@@ -60,12 +70,69 @@ impl<I2CError> ufmt::uDisplay for Error<I2CError>
                     }
                 }
             }
+            Error::SpiError(kind) => {
+                fmt.write_str("spi error: ")?;
+                match kind {
+                    spi::ErrorKind::Overrun => {
+                        fmt.write_str("overrun")
+                    }
+                    spi::ErrorKind::ModeFault => {
+                        fmt.write_str("mode fault")
+                    }
+                    spi::ErrorKind::FrameFormat => {
+                        fmt.write_str("frame format")
+                    }
+                    spi::ErrorKind::ChipSelectFault => {
+                        fmt.write_str("chip select fault")
+                    }
+                    spi::ErrorKind::Other => {
+                        fmt.write_str("other")
+                    }
+                    _ => {
+                        fmt.write_str("unknown")
+                    }
+                }
+            }
+            Error::PinError => {
+                fmt.write_str("pin error")
+            }
             Error::OutsideScreenAccess { x, y } => {
                 fmt.write_str("oob: ")?;
                 x.fmt(fmt)?;
                 fmt.write_str(", ")?;
                 y.fmt(fmt)
             }
+            Error::InvalidChar(character) => {
+                fmt.write_str("invalid char: ")?;
+                fmt.write_char(*character)
+            }
+            Error::InvalidScrollRange => {
+                fmt.write_str("invalid scroll range")
+            }
+            Error::ShadowTooSmall => {
+                fmt.write_str("shadow too small")
+            }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(all(test, feature = "string-errors"))]
+mod tests {
+    use core::convert::Infallible;
+    use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+    use embedded_hal::spi;
+    use crate::error::UDisplayError;
+    use crate::ssd1306_error::Error;
+    use crate::test_support::ufmt_string;
+
+    #[test]
+    fn format_test() {
+        let error: Error<Infallible> = Error::SpiError(spi::ErrorKind::Overrun);
+        assert_eq!(ufmt_string(&error), "spi error: overrun");
+        assert_eq!(ufmt_string(&UDisplayError::from(error)), "SSD1306 error: spi error: overrun");
+        let error: Error<ErrorKind> = Error::I2cError(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+        assert_eq!(ufmt_string(&error), "i2c error: no acknowledge, address");
+        let error: Error<ErrorKind> = Error::I2cError(ErrorKind::Bus);
+        assert_eq!(ufmt_string(&UDisplayError::from(error)), "SSD1306 error: i2c error: bus");
+    }
+}
@@ -2,6 +2,270 @@ pub const FONT_WIDTH: u16 = 5;
 pub const FONT_WIDTH_1: u16 = 6;
 pub const FONT_HEIGHT: u16 = 7;
 pub const FONT_HEIGHT_1: u16 = 8;
+
+/// A bitmap font with glyphs indexed by code page 437 code, see `cp437`. Glyphs are stored like the display RAM: column after column, each column
+/// `pages()` bytes from top to bottom with the least significant bit on top.
+#[derive(Clone, Copy, Debug)]
+pub struct Font {
+    /// Code of the first glyph in `data`
+    pub first_char: u8,
+    /// Code of the last glyph in `data`
+    pub last_char: u8,
+    /// Glyph height in pixels
+    pub height: u8,
+    /// Distance between the tops of two lines of text
+    pub line_height: u8,
+    /// Empty columns after each glyph
+    pub spacing: u8,
+    pub widths: GlyphWidths,
+    pub data: &'static [u8],
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum GlyphWidths {
+    /// Every glyph is this many columns wide and they follow each other in `data`.
+    Monospace(u8),
+    /// One entry per glyph from `first_char` to `last_char`.
+    Proportional(&'static [GlyphInfo]),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlyphInfo {
+    /// Index of the glyph's first byte in `data`
+    pub offset: u16,
+    pub width: u8,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Glyph<'a> {
+    pub width: u8,
+    pub columns: &'a [u8],
+}
+
+impl Font {
+    /// Bytes per glyph column.
+    pub const fn pages(&self) -> usize {
+        self.height.div_ceil(8) as usize
+    }
+
+    /// The glyph for `code`, or `None` if the font doesn't have it.
+    pub fn glyph(&self, code: u8) -> Option<Glyph<'static>> {
+        if code < self.first_char || code > self.last_char {
+            return None;
+        }
+        let index = (code - self.first_char) as usize;
+        let (offset, width) = match self.widths {
+            GlyphWidths::Monospace(width) => (index * width as usize * self.pages(), width),
+            GlyphWidths::Proportional(glyphs) => {
+                let info = glyphs.get(index)?;
+                (info.offset as usize, info.width)
+            }
+        };
+        let columns = self.data.get(offset..offset + width as usize * self.pages())?;
+        Some(Glyph { width, columns })
+    }
+
+    /// Columns the cursor moves by after drawing `code`, including spacing.
+    pub fn advance(&self, code: u8) -> u8 {
+        self.glyph(code).map_or(0, |glyph| glyph.width + self.spacing)
+    }
+
+    /// Whether `column`, `row` of the glyph is set.
+    pub fn is_set(&self, glyph: &Glyph, column: u8, row: u8) -> bool {
+        let byte = glyph.columns[column as usize * self.pages() + row as usize / 8];
+        byte & (1 << (row % 8)) != 0
+    }
+}
+
+/// Maps a character to its code page 437 code, which is also its index in `FONT`. ASCII maps to
+/// itself. Returns `None` for characters CP437 has no glyph for.
+pub fn cp437(character: char) -> Option<u8> {
+    if character.is_ascii() {
+        return Some(character as u8);
+    }
+    let code = match character {
+        '☺' => 0x01,
+        '☻' => 0x02,
+        '♥' => 0x03,
+        '♦' => 0x04,
+        '♣' => 0x05,
+        '♠' => 0x06,
+        '•' => 0x07,
+        '◘' => 0x08,
+        '○' => 0x09,
+        '◙' => 0x0A,
+        '♂' => 0x0B,
+        '♀' => 0x0C,
+        '♪' => 0x0D,
+        '♫' => 0x0E,
+        '☼' => 0x0F,
+        '►' => 0x10,
+        '◄' => 0x11,
+        '↕' => 0x12,
+        '‼' => 0x13,
+        '¶' => 0x14,
+        '§' => 0x15,
+        '▬' => 0x16,
+        '↨' => 0x17,
+        '↑' => 0x18,
+        '↓' => 0x19,
+        '→' => 0x1A,
+        '←' => 0x1B,
+        '∟' => 0x1C,
+        '↔' => 0x1D,
+        '▲' => 0x1E,
+        '▼' => 0x1F,
+        '⌂' => 0x7F,
+        'Ç' => 0x80,
+        'ü' => 0x81,
+        'é' => 0x82,
+        'â' => 0x83,
+        'ä' => 0x84,
+        'à' => 0x85,
+        'å' => 0x86,
+        'ç' => 0x87,
+        'ê' => 0x88,
+        'ë' => 0x89,
+        'è' => 0x8A,
+        'ï' => 0x8B,
+        'î' => 0x8C,
+        'ì' => 0x8D,
+        'Ä' => 0x8E,
+        'Å' => 0x8F,
+        'É' => 0x90,
+        'æ' => 0x91,
+        'Æ' => 0x92,
+        'ô' => 0x93,
+        'ö' => 0x94,
+        'ò' => 0x95,
+        'û' => 0x96,
+        'ù' => 0x97,
+        'ÿ' => 0x98,
+        'Ö' => 0x99,
+        'Ü' => 0x9A,
+        '¢' => 0x9B,
+        '£' => 0x9C,
+        '¥' => 0x9D,
+        '₧' => 0x9E,
+        'ƒ' => 0x9F,
+        'á' => 0xA0,
+        'í' => 0xA1,
+        'ó' => 0xA2,
+        'ú' => 0xA3,
+        'ñ' => 0xA4,
+        'Ñ' => 0xA5,
+        'ª' => 0xA6,
+        'º' => 0xA7,
+        '¿' => 0xA8,
+        '⌐' => 0xA9,
+        '¬' => 0xAA,
+        '½' => 0xAB,
+        '¼' => 0xAC,
+        '¡' => 0xAD,
+        '«' => 0xAE,
+        '»' => 0xAF,
+        '░' => 0xB0,
+        '▒' => 0xB1,
+        '▓' => 0xB2,
+        '│' => 0xB3,
+        '┤' => 0xB4,
+        '╡' => 0xB5,
+        '╢' => 0xB6,
+        '╖' => 0xB7,
+        '╕' => 0xB8,
+        '╣' => 0xB9,
+        '║' => 0xBA,
+        '╗' => 0xBB,
+        '╝' => 0xBC,
+        '╜' => 0xBD,
+        '╛' => 0xBE,
+        '┐' => 0xBF,
+        '└' => 0xC0,
+        '┴' => 0xC1,
+        '┬' => 0xC2,
+        '├' => 0xC3,
+        '─' => 0xC4,
+        '┼' => 0xC5,
+        '╞' => 0xC6,
+        '╟' => 0xC7,
+        '╚' => 0xC8,
+        '╔' => 0xC9,
+        '╩' => 0xCA,
+        '╦' => 0xCB,
+        '╠' => 0xCC,
+        '═' => 0xCD,
+        '╬' => 0xCE,
+        '╧' => 0xCF,
+        '╨' => 0xD0,
+        '╤' => 0xD1,
+        '╥' => 0xD2,
+        '╙' => 0xD3,
+        '╘' => 0xD4,
+        '╒' => 0xD5,
+        '╓' => 0xD6,
+        '╫' => 0xD7,
+        '╪' => 0xD8,
+        '┘' => 0xD9,
+        '┌' => 0xDA,
+        '█' => 0xDB,
+        '▄' => 0xDC,
+        '▌' => 0xDD,
+        '▐' => 0xDE,
+        '▀' => 0xDF,
+        'α' => 0xE0,
+        'ß' => 0xE1,
+        'Γ' => 0xE2,
+        'π' => 0xE3,
+        'Σ' => 0xE4,
+        'σ' => 0xE5,
+        'µ' => 0xE6,
+        'τ' => 0xE7,
+        'Φ' => 0xE8,
+        'Θ' => 0xE9,
+        'Ω' => 0xEA,
+        'δ' => 0xEB,
+        '∞' => 0xEC,
+        'φ' => 0xED,
+        'ε' => 0xEE,
+        '∩' => 0xEF,
+        '≡' => 0xF0,
+        '±' => 0xF1,
+        '≥' => 0xF2,
+        '≤' => 0xF3,
+        '⌠' => 0xF4,
+        '⌡' => 0xF5,
+        '÷' => 0xF6,
+        '≈' => 0xF7,
+        '°' => 0xF8,
+        '∙' => 0xF9,
+        '·' => 0xFA,
+        '√' => 0xFB,
+        'ⁿ' => 0xFC,
+        '²' => 0xFD,
+        '■' => 0xFE,
+        '\u{A0}' => 0xFF,
+        // Look-alikes without a glyph of their own
+        'β' => 0xE1,
+        'μ' => 0xE6,
+        '∅' => 0xED,
+        '∈' => 0xEE,
+        '\u{2126}' => 0xEA,
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// The classic Adafruit GFX 5x7 font with the full CP437 set. Descenders use an eighth row.
+pub const FONT_5X7: Font = Font {
+    first_char: 0,
+    last_char: 255,
+    height: FONT_HEIGHT_1 as u8,
+    line_height: FONT_HEIGHT_1 as u8,
+    spacing: (FONT_WIDTH_1 - FONT_WIDTH) as u8,
+    widths: GlyphWidths::Monospace(FONT_WIDTH as u8),
+    data: &FONT,
+};
+
 pub const FONT: [u8; 1280] = [
     0x00, 0x00, 0x00, 0x00, 0x00, 0x3E, 0x5B, 0x4F, 0x5B, 0x3E, 0x3E, 0x6B,
     0x4F, 0x6B, 0x3E, 0x1C, 0x3E, 0x7C, 0x3E, 0x1C, 0x18, 0x3C, 0x7E, 0x3C,
@@ -118,3 +382,61 @@ pub const FONT: [u8; 1280] = [
     0x3C, 0x00, 0x00, 0x00, 0x00, 0x00 // #255 NBSP
 
 ];
+
+#[cfg(test)]
+mod tests {
+    use crate::ssd1306_font::{cp437, Font, GlyphInfo, GlyphWidths, FONT_5X7};
+
+    #[test]
+    fn monospace_glyph_test() {
+        let glyph = FONT_5X7.glyph(b'A').unwrap();
+        assert_eq!(glyph.width, 5);
+        assert_eq!(glyph.columns, &[0x7C, 0x12, 0x11, 0x12, 0x7C]);
+        assert!(FONT_5X7.is_set(&glyph, 0, 2));
+        assert!(!FONT_5X7.is_set(&glyph, 0, 1));
+        assert_eq!(FONT_5X7.advance(b'A'), 6);
+    }
+
+    #[test]
+    fn proportional_glyph_test() {
+        static GLYPHS: [GlyphInfo; 2] = [GlyphInfo { offset: 0, width: 1 }, GlyphInfo { offset: 2, width: 3 }];
+        static DATA: [u8; 8] = [0xFF, 0x0F, 0x01, 0x00, 0x02, 0x00, 0x04, 0x80];
+        let font = Font {
+            first_char: b'i',
+            last_char: b'j',
+            height: 12,
+            line_height: 14,
+            spacing: 1,
+            widths: GlyphWidths::Proportional(&GLYPHS),
+            data: &DATA,
+        };
+        assert_eq!(font.pages(), 2);
+        assert_eq!(font.glyph(b'i').unwrap().columns, &[0xFF, 0x0F]);
+        let glyph = font.glyph(b'j').unwrap();
+        assert_eq!(glyph.width, 3);
+        assert!(font.is_set(&glyph, 2, 2));
+        assert!(font.is_set(&glyph, 2, 15));
+        assert_eq!(font.advance(b'j'), 4);
+        assert!(font.glyph(b'h').is_none());
+        assert!(font.glyph(b'k').is_none());
+    }
+
+    #[test]
+    fn cp437_test() {
+        assert_eq!(cp437('A'), Some(0x41));
+        assert_eq!(cp437('\n'), Some(0x0A));
+        assert_eq!(cp437('☺'), Some(0x01));
+        assert_eq!(cp437('⌂'), Some(0x7F));
+        assert_eq!(cp437('Ç'), Some(0x80));
+        assert_eq!(cp437('é'), Some(0x82));
+        assert_eq!(cp437('░'), Some(0xB0));
+        assert_eq!(cp437('╬'), Some(0xCE));
+        assert_eq!(cp437('°'), Some(0xF8));
+        assert_eq!(cp437('→'), Some(0x1A));
+        assert_eq!(cp437('ß'), Some(0xE1));
+        assert_eq!(cp437('β'), Some(0xE1));
+        assert_eq!(cp437('\u{A0}'), Some(0xFF));
+        assert_eq!(cp437('€'), None);
+        assert_eq!(cp437('字'), None);
+    }
+}
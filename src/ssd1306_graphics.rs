@@ -0,0 +1,79 @@
+use embedded_graphics_core::pixelcolor::BinaryColor;
+use embedded_graphics_core::prelude::{DrawTarget, OriginDimensions, Pixel, Size};
+use embedded_graphics_core::primitives::Rectangle;
+use crate::ssd1306::DisplayDriver;
+use crate::ssd1306_error::Error;
+use crate::ssd1306_interface::DisplayInterface;
+use crate::ssd1306_registers::{BLACK, WHITE};
+use crate::ssd1306_size::DisplaySize;
+
+/// Lets embedded-graphics draw into the framebuffer. Nothing is sent to the display until `display` is called.
+impl<'buffer, DI: DisplayInterface, SIZE: DisplaySize> DrawTarget for DisplayDriver<'buffer, DI, SIZE> {
+    type Color = BinaryColor;
+    type Error = Error<DI::Error>;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where I: IntoIterator<Item=Pixel<Self::Color>> {
+        for Pixel(point, color) in pixels {
+            self.draw_pixel(to_i16(point.x), to_i16(point.y), to_color(color))?;
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let width = area.size.width.min(i16::MAX as u32) as i16;
+        let height = area.size.height.min(i16::MAX as u32) as i16;
+        self.draw_fill_rect(to_i16(area.top_left.x), to_i16(area.top_left.y), width, height, to_color(color))
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.fill_screen(to_color(color));
+        Ok(())
+    }
+}
+
+impl<'buffer, DI: DisplayInterface, SIZE: DisplaySize> OriginDimensions for DisplayDriver<'buffer, DI, SIZE> {
+    fn size(&self) -> Size {
+        Size::new(self.width() as u32, self.height() as u32)
+    }
+}
+
+/// Coordinates beyond the i16 range are far off screen, so clamping them keeps them clipped.
+fn to_i16(coordinate: i32) -> i16 {
+    coordinate.clamp(i16::MIN as i32, i16::MAX as i32) as i16
+}
+
+fn to_color(color: BinaryColor) -> u8 {
+    match color {
+        BinaryColor::On => WHITE,
+        BinaryColor::Off => BLACK,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics_core::pixelcolor::BinaryColor;
+    use embedded_graphics_core::prelude::{DrawTarget, OriginDimensions, Pixel, Point, Size};
+    use embedded_graphics_core::primitives::Rectangle;
+    use crate::ssd1306_size::{DisplaySize, DisplaySize128x64};
+    use crate::test_support::noop_display;
+
+    #[test]
+    fn draw_target_test() {
+        let mut buffer = [0x00; DisplaySize128x64::BUFFER_SIZE];
+        {
+            let mut display = noop_display(&mut buffer);
+            assert_eq!(display.size(), Size::new(128, 64));
+            display.fill_solid(&Rectangle::new(Point::new(-2, 0), Size::new(4, 8)), BinaryColor::On).unwrap();
+            display.draw_iter([
+                Pixel(Point::new(1, 0), BinaryColor::Off),
+                Pixel(Point::new(2, 9), BinaryColor::On),
+                Pixel(Point::new(100_000, 9), BinaryColor::On),
+            ]).unwrap();
+        }
+        assert_eq!(&buffer[0..2], &[0xFF, 0x00]);
+        assert_eq!(&buffer[8..10], &[0xFE, 0x00]);
+        assert_eq!(&buffer[16..18], &[0x00, 0b10]);
+        assert_eq!(buffer.iter().filter(|byte| **byte != 0).count(), 3);
+    }
+}
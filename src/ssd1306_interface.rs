@@ -0,0 +1,159 @@
+use core::convert::Infallible;
+use embedded_hal::digital::OutputPin;
+use embedded_hal::i2c::{I2c, Operation};
+use embedded_hal::spi::{Error as _, SpiDevice};
+use crate::ssd1306_error::Error;
+
+const DEFAULT_ADDRESS: u8 = 0x3C;
+
+/// The bus the display is connected to. Commands and display RAM data are told apart by a
+/// control byte on I2C and by the D/C pin on SPI.
+pub trait DisplayInterface {
+    type Error: core::fmt::Debug;
+
+    fn write_command(&mut self, command: &[u8]) -> Result<(), Error<Self::Error>>;
+    fn write_data(&mut self, data: &[u8]) -> Result<(), Error<Self::Error>>;
+}
+
+pub struct I2cInterface<I2C> {
+    i2c: I2C,
+    address: u8,
+}
+
+impl<I2C: I2c> I2cInterface<I2C> {
+    /// Uses address 0x3C if `address` is `None`.
+    pub fn new(i2c: I2C, address: Option<u8>) -> Self {
+        Self {
+            i2c,
+            address: address.unwrap_or(DEFAULT_ADDRESS),
+        }
+    }
+
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+}
+
+impl<I2C: I2c> DisplayInterface for I2cInterface<I2C> {
+    type Error = I2C::Error;
+
+    fn write_command(&mut self, command: &[u8]) -> Result<(), Error<Self::Error>> {
+        self.i2c.transaction(self.address, &mut [Operation::Write(&[0x00]), Operation::Write(command)])?;
+        Ok(())
+    }
+
+    fn write_data(&mut self, data: &[u8]) -> Result<(), Error<Self::Error>> {
+        self.i2c.transaction(self.address, &mut [Operation::Write(&[0x40]), Operation::Write(data)])?;
+        Ok(())
+    }
+}
+
+/// 4-wire SPI. Chip select is handled by the `SpiDevice`, `dc` is low for commands and high for data.
+pub struct SpiInterface<SPI, DC> {
+    spi: SPI,
+    dc: DC,
+}
+
+impl<SPI: SpiDevice, DC: OutputPin> SpiInterface<SPI, DC> {
+    pub fn new(spi: SPI, dc: DC) -> Self {
+        Self { spi, dc }
+    }
+
+    pub fn release(self) -> (SPI, DC) {
+        (self.spi, self.dc)
+    }
+}
+
+impl<SPI: SpiDevice, DC: OutputPin> DisplayInterface for SpiInterface<SPI, DC> {
+    /// SPI errors are kept as their `ErrorKind` in `Error::SpiError`, so there is no bus error type.
+    type Error = Infallible;
+
+    fn write_command(&mut self, command: &[u8]) -> Result<(), Error<Self::Error>> {
+        self.dc.set_low().map_err(|_| Error::PinError)?;
+        self.spi.write(command).map_err(|error| Error::SpiError(error.kind()))
+    }
+
+    fn write_data(&mut self, data: &[u8]) -> Result<(), Error<Self::Error>> {
+        self.dc.set_high().map_err(|_| Error::PinError)?;
+        self.spi.write(data).map_err(|error| Error::SpiError(error.kind()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+    use core::convert::Infallible;
+    use embedded_hal::digital::{self, OutputPin};
+    use embedded_hal::spi::{self, SpiDevice};
+    use crate::ssd1306_interface::{DisplayInterface, I2cInterface, SpiInterface};
+    use crate::test_support::RecordingI2c;
+
+    struct DcPin<'a>(&'a Cell<bool>);
+
+    impl digital::ErrorType for DcPin<'_> {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for DcPin<'_> {
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            self.0.set(false);
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            self.0.set(true);
+            Ok(())
+        }
+    }
+
+    /// Records every byte together with the D/C level at the time it was written.
+    struct RecordingSpi<'a> {
+        dc: &'a Cell<bool>,
+        log: [(bool, u8); 16],
+        length: usize,
+    }
+
+    impl spi::ErrorType for RecordingSpi<'_> {
+        type Error = spi::ErrorKind;
+    }
+
+    impl SpiDevice for RecordingSpi<'_> {
+        fn transaction(&mut self, operations: &mut [spi::Operation<'_, u8>]) -> Result<(), Self::Error> {
+            for operation in operations {
+                if let spi::Operation::Write(bytes) = operation {
+                    for byte in bytes.iter() {
+                        self.log[self.length] = (self.dc.get(), *byte);
+                        self.length += 1;
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn i2c_interface_test() {
+        let mut interface = I2cInterface::new(RecordingI2c::new(), None);
+        interface.write_command(&[0xAE, 0xD5]).unwrap();
+        interface.write_data(&[0xFF]).unwrap();
+        let i2c = interface.release();
+        assert_eq!(i2c.transactions, [(0x3C, vec![0x00, 0xAE, 0xD5]), (0x3C, vec![0x40, 0xFF])]);
+
+        let mut interface = I2cInterface::new(RecordingI2c::new(), Some(0x3D));
+        interface.write_command(&[0xAF]).unwrap();
+        let i2c = interface.release();
+        assert_eq!(i2c.transactions, [(0x3D, vec![0x00, 0xAF])]);
+    }
+
+    #[test]
+    fn spi_interface_test() {
+        let dc = Cell::new(true);
+        let spi = RecordingSpi { dc: &dc, log: [(false, 0); 16], length: 0 };
+        let mut interface = SpiInterface::new(spi, DcPin(&dc));
+        interface.write_command(&[0xAE, 0xD5]).unwrap();
+        interface.write_data(&[0x12, 0x34]).unwrap();
+        interface.write_command(&[0xAF]).unwrap();
+        let (spi, _) = interface.release();
+        assert_eq!(&spi.log[..spi.length], &[(false, 0xAE), (false, 0xD5), (true, 0x12), (true, 0x34), (false, 0xAF)]);
+    }
+}
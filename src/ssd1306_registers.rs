@@ -36,7 +36,4 @@ pub const VERTICAL_AND_RIGHT_HORIZONTAL_SCROLL: u8 = 0x29; ///< Init diag scroll
 pub const VERTICAL_AND_LEFT_HORIZONTAL_SCROLL: u8 = 0x2A;  ///< Init diag scroll
 pub const DEACTIVATE_SCROLL: u8 = 0x2E;                    ///< Stop scroll
 pub const ACTIVATE_SCROLL: u8 = 0x2F;                      ///< Start scroll
-pub const SET_VERTICAL_SCROLL_AREA: u8 = 0xA3;             ///< Set scroll range
-
-pub const LCDWIDTH: u16 = 128;
-pub const LCDHEIGHT: u16 = 64;
+pub const SET_VERTICAL_SCROLL_AREA: u8 = 0xA3;             //< Set scroll range
@@ -0,0 +1,42 @@
+/// Geometry of an SSD1306 panel. Determines the framebuffer size and the multiplex ratio,
+/// COM pins configuration and address window sent to the display.
+pub trait DisplaySize {
+    const WIDTH: u8;
+    const HEIGHT: u8;
+    /// Value for SETCOMPINS, depending on how the panel's rows are wired to the COM pins
+    const COM_PINS: u8;
+    const PAGES: u8 = Self::HEIGHT / 8;
+    const BUFFER_SIZE: usize = Self::WIDTH as usize * Self::PAGES as usize;
+    /// A framebuffer of `BUFFER_SIZE` bytes
+    type Buffer: AsRef<[u8]> + AsMut<[u8]>;
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DisplaySize128x64;
+
+impl DisplaySize for DisplaySize128x64 {
+    const WIDTH: u8 = 128;
+    const HEIGHT: u8 = 64;
+    const COM_PINS: u8 = 0x12;
+    type Buffer = [u8; 128 * 64 / 8];
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DisplaySize128x32;
+
+impl DisplaySize for DisplaySize128x32 {
+    const WIDTH: u8 = 128;
+    const HEIGHT: u8 = 32;
+    const COM_PINS: u8 = 0x02;
+    type Buffer = [u8; 128 * 32 / 8];
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DisplaySize96x16;
+
+impl DisplaySize for DisplaySize96x16 {
+    const WIDTH: u8 = 96;
+    const HEIGHT: u8 = 16;
+    const COM_PINS: u8 = 0x02;
+    type Buffer = [u8; 96 * 16 / 8];
+}
@@ -0,0 +1,202 @@
+//! Helpers for the host-side tests: mock buses and text renderings of the framebuffer that are
+//! compared against the files in `host-tests/golden`.
+
+use std::fmt::Write;
+use std::string::String;
+use std::collections::VecDeque;
+use std::vec::Vec;
+use embedded_hal::i2c::{ErrorKind, ErrorType, I2c, Operation};
+use crate::bmi160_registers::FIFO_DATA;
+use crate::ssd1306::DisplayDriver;
+use crate::ssd1306_config::Config;
+use crate::ssd1306_interface::I2cInterface;
+use crate::ssd1306_size::{DisplaySize, DisplaySize128x64};
+
+/// Accepts every transaction and ignores it.
+pub struct NoopI2c;
+
+impl ErrorType for NoopI2c {
+    type Error = ErrorKind;
+}
+
+impl I2c for NoopI2c {
+    fn transaction(&mut self, _address: u8, _operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// A 128x64 display with the default config, on a bus that ignores everything.
+pub fn noop_display(buffer: &mut <DisplaySize128x64 as DisplaySize>::Buffer) -> DisplayDriver<'_, I2cInterface<NoopI2c>> {
+    DisplayDriver::new(I2cInterface::new(NoopI2c, None), buffer, DisplaySize128x64, Config::default()).unwrap()
+}
+
+/// Records the address and written bytes of every transaction.
+#[derive(Default)]
+pub struct RecordingI2c {
+    pub transactions: Vec<(u8, Vec<u8>)>,
+}
+
+impl RecordingI2c {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// One line per transaction: the address followed by the bytes, in hex.
+    pub fn dump(&self) -> String {
+        let mut dump = String::new();
+        for (address, bytes) in &self.transactions {
+            write!(dump, "{:02X}:", address).unwrap();
+            for byte in bytes {
+                write!(dump, " {:02X}", byte).unwrap();
+            }
+            dump.push('\n');
+        }
+        dump
+    }
+}
+
+impl ErrorType for RecordingI2c {
+    type Error = ErrorKind;
+}
+
+impl I2c for RecordingI2c {
+    fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+        let mut written = Vec::new();
+        for operation in operations {
+            match operation {
+                Operation::Write(bytes) => written.extend_from_slice(bytes),
+                Operation::Read(bytes) => bytes.fill(0x00),
+            }
+        }
+        self.transactions.push((address, written));
+        Ok(())
+    }
+}
+
+/// A device with 128 byte wide registers. A write sets the registers following the address in
+/// its first byte, and a read returns them. Reads from the BMI160's FIFO_DATA register take
+/// bytes from `fifo` instead, and return 0x80 once it's empty like the sensor does.
+pub struct RegisterI2c {
+    pub registers: [u8; 128],
+    pub fifo: VecDeque<u8>,
+    pointer: usize,
+}
+
+impl RegisterI2c {
+    pub fn new() -> Self {
+        Self { registers: [0x00; 128], fifo: VecDeque::new(), pointer: 0 }
+    }
+}
+
+impl ErrorType for RegisterI2c {
+    type Error = ErrorKind;
+}
+
+impl I2c for RegisterI2c {
+    fn transaction(&mut self, _address: u8, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+        for operation in operations {
+            match operation {
+                Operation::Write(bytes) => {
+                    let Some((register, values)) = bytes.split_first() else {
+                        continue;
+                    };
+                    self.pointer = *register as usize;
+                    self.registers[self.pointer..self.pointer + values.len()].copy_from_slice(values);
+                }
+                Operation::Read(bytes) if self.pointer == FIFO_DATA as usize => {
+                    bytes.fill_with(|| self.fifo.pop_front().unwrap_or(0x80));
+                }
+                Operation::Read(bytes) => {
+                    bytes.copy_from_slice(&self.registers[self.pointer..self.pointer + bytes.len()]);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Formats `value` with ufmt, which has no `String` support without its std feature.
+#[cfg(feature = "string-errors")]
+pub fn ufmt_string(value: &impl ufmt::uDisplay) -> String {
+    struct Writer(String);
+
+    impl ufmt::uWrite for Writer {
+        type Error = core::convert::Infallible;
+
+        fn write_str(&mut self, s: &str) -> Result<(), Self::Error> {
+            self.0.push_str(s);
+            Ok(())
+        }
+    }
+
+    let mut writer = Writer(String::new());
+    value.fmt(&mut ufmt::Formatter::new(&mut writer)).unwrap();
+    writer.0
+}
+
+/// The framebuffer as one line per pixel row, `#` for set pixels and `.` for clear ones.
+pub fn render_ascii<SIZE: DisplaySize>(buffer: &[u8]) -> String {
+    let mut text = String::new();
+    for y in 0..SIZE::HEIGHT as usize {
+        for x in 0..SIZE::WIDTH as usize {
+            text.push(if is_set::<SIZE>(buffer, x, y) { '#' } else { '.' });
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// The framebuffer as a plain PBM image. Set pixels are 1, which image viewers show as black.
+pub fn render_pbm<SIZE: DisplaySize>(buffer: &[u8]) -> String {
+    let mut text = String::new();
+    writeln!(text, "P1\n{} {}", SIZE::WIDTH, SIZE::HEIGHT).unwrap();
+    for y in 0..SIZE::HEIGHT as usize {
+        let row: Vec<&str> = (0..SIZE::WIDTH as usize)
+            .map(|x| if is_set::<SIZE>(buffer, x, y) { "1" } else { "0" })
+            .collect();
+        writeln!(text, "{}", row.join(" ")).unwrap();
+    }
+    text
+}
+
+fn is_set<SIZE: DisplaySize>(buffer: &[u8], x: usize, y: usize) -> bool {
+    buffer[x * SIZE::PAGES as usize + y / 8] & (1 << (y % 8)) != 0
+}
+
+/// Compares `actual` with the golden file `name`. Run the tests with `UPDATE_GOLDEN=1` to write
+/// the files instead, after checking that the new output is right.
+pub fn assert_golden(name: &str, actual: &str) {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("golden").join(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|error| panic!("can't read {}: {}, run with UPDATE_GOLDEN=1 to create it", path.display(), error));
+    if expected != actual {
+        panic!("{} doesn't match, got:\n{}", path.display(), actual);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ssd1306_size::DisplaySize96x16;
+    use crate::test_support::{render_ascii, render_pbm};
+
+    #[test]
+    fn render_test() {
+        let mut buffer = [0x00; 96 * 2];
+        buffer[0] = 0b1;
+        buffer[2 + 1] = 0b1000_0000;
+        let ascii = render_ascii::<DisplaySize96x16>(&buffer);
+        let lines: Vec<&str> = ascii.lines().collect();
+        assert_eq!(lines.len(), 16);
+        assert!(lines[0].starts_with("#.."));
+        assert!(lines[15].starts_with(".#."));
+        assert_eq!(ascii.matches('#').count(), 2);
+
+        let pbm = render_pbm::<DisplaySize96x16>(&buffer);
+        assert!(pbm.starts_with("P1\n96 16\n1 0 0"));
+        assert_eq!(pbm.lines().count(), 18);
+    }
+}